@@ -3,7 +3,7 @@ use std::io::Cursor;
 use exact_reader::{ExactReader, File};
 
 fn into_file(value: Cursor<Vec<u8>>) -> File<Cursor<Vec<u8>>> {
-    let len = value.get_ref().len();
+    let len = value.get_ref().len() as u64;
     File {
         file: value,
         size: len,
@@ -3,7 +3,7 @@ use std::io::{Cursor, Read};
 use exact_reader::{File, MultiFile};
 
 fn into_file(value: Cursor<Vec<u8>>) -> File<Cursor<Vec<u8>>> {
-    let len = value.get_ref().len();
+    let len = value.get_ref().len() as u64;
     File {
         file: value,
         size: len,
@@ -17,6 +17,6 @@ fn main() {
 
     let mut file = MultiFile::new(vec![into_file(a), into_file(b)]);
     let mut buf = [0u8; 4];
-    file.read(&mut buf).unwrap();
+    file.read_exact(&mut buf).unwrap();
     assert_eq!(buf, [1, 2, 3, 4])
 }
@@ -0,0 +1,330 @@
+use std::{
+    future::poll_fn,
+    io::SeekFrom,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncSeek, ReadBuf};
+
+use crate::{
+    buffer::{BufferState, Fetch, DEFAULT_RESERVE_SIZE},
+    multifile::{File, MultiFile},
+    stats::Stats,
+};
+
+/// The `AsyncExactReader` struct is the asynchronous counterpart of `ExactReader`,
+/// reading from `AsyncRead + AsyncSeek` file(s) with the same reservation logic.
+pub struct AsyncExactReader<R> {
+    /// The inner reader for the file.
+    file: R,
+
+    /// The buffer and the reservation state.
+    state: BufferState,
+
+    /// The target of the seek in progress on the inner reader.
+    seeking: Option<u64>,
+    /// The position to be returned by `poll_complete`.
+    seek_result: Option<u64>,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncExactReader<MultiFile<R>> {
+    /// Creates a new `AsyncExactReader` instance for reading data from multiple files.
+    pub fn new_multi(file: MultiFile<R>) -> Self {
+        let size = file.size();
+        Self::with_size(file, size, 0)
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncExactReader<File<R>> {
+    /// Creates a new `AsyncExactReader` instance for reading data from a single file.
+    pub fn new_single(file: File<R>) -> Self {
+        let size = file.size;
+        Self::with_size(file, size, 0)
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncExactReader<R> {
+    /// Creates a new `AsyncExactReader` instance for any reader.
+    ///
+    /// The size is discovered by seeking to the end of the reader,
+    /// reading starts from the current position of the reader.
+    pub async fn new(mut file: R) -> std::io::Result<Self> {
+        let offset = seek(&mut file, SeekFrom::Current(0)).await?;
+        let size = seek(&mut file, SeekFrom::End(0)).await?;
+        if offset != size {
+            seek(&mut file, SeekFrom::Start(offset)).await?;
+        }
+
+        Ok(Self::with_size(file, size, offset))
+    }
+
+    /// Creates a new `AsyncExactReader` with a known size, the inner reader must be at `offset`.
+    fn with_size(file: R, size: u64, offset: u64) -> Self {
+        Self {
+            file,
+            state: BufferState::new(size, offset),
+            seeking: None,
+            seek_result: None,
+        }
+    }
+
+    /// The total size of the file(s) in bytes.
+    pub fn size(&self) -> u64 {
+        self.state.size
+    }
+
+    /// Limits the buffer size, evicting consumed bytes when a reservation would exceed it.
+    ///
+    /// See [`ExactReader::set_max_buffer_size`](crate::ExactReader::set_max_buffer_size).
+    pub fn set_max_buffer_size(&mut self, max_buffer_size: Option<usize>) {
+        self.state.max_buffer_size = max_buffer_size;
+    }
+
+    /// Keeps the last `backlog` consumed bytes on eviction, so that short seeks back stay cached.
+    pub fn set_backlog(&mut self, backlog: usize) {
+        self.state.backlog = backlog;
+    }
+
+    /// Keeps up to `max_ranges` ranges, `max_size` bytes in total, when a seek leaves the buffer.
+    ///
+    /// See [`ExactReader::set_cache_limits`](crate::ExactReader::set_cache_limits).
+    pub fn set_cache_limits(&mut self, max_ranges: usize, max_size: usize) {
+        self.state.cache.set_limits(max_ranges, max_size);
+    }
+
+    /// The I/O counters since the creation of the reader or the last [`AsyncExactReader::reset_stats`].
+    pub fn stats(&self) -> Stats {
+        self.state.stats
+    }
+
+    /// Resets the I/O counters.
+    pub fn reset_stats(&mut self) {
+        self.state.stats = Stats::default();
+    }
+
+    /// Reserves and caches space in the buffer for future reads,
+    /// returning the number of bytes cached from the current offset.
+    ///
+    /// The returned value is less than `reserve_size` only when the end of the file(s) is reached.
+    pub async fn reserve(&mut self, reserve_size: usize) -> std::io::Result<usize> {
+        poll_fn(|cx| self.poll_reserve(cx, reserve_size)).await
+    }
+
+    /// Returns the next `peek_size` bytes without advancing the offset.
+    ///
+    /// The data is split in two slices when it wraps around the ring buffer,
+    /// the slices are shorter than `peek_size` only at the end of the file(s).
+    pub async fn peek(&mut self, peek_size: usize) -> std::io::Result<(&[u8], &[u8])> {
+        let size = self.reserve(peek_size).await?;
+        Ok(self.state.unread(size))
+    }
+
+    /// Polls a reservation, see [`AsyncExactReader::reserve`].
+    ///
+    /// A reservation left pending is resumed by the next poll, even with a different size.
+    pub fn poll_reserve(
+        &mut self,
+        cx: &mut Context<'_>,
+        reserve_size: usize,
+    ) -> Poll<std::io::Result<usize>> {
+        if let Some(fetch) = self.state.pending() {
+            ready!(self.poll_fetch(cx))?;
+            if let Fetch::Back { .. } = fetch {
+                return Poll::Ready(Ok(self.state.reserved(reserve_size)));
+            }
+        }
+
+        if let Some(fetch) = self.state.seek_fetch(reserve_size) {
+            self.state.begin(fetch);
+            ready!(self.poll_fetch(cx))?;
+        }
+        if let Some(fetch) = self.state.back_fetch(reserve_size) {
+            self.state.begin(fetch);
+            ready!(self.poll_fetch(cx))?;
+        }
+
+        Poll::Ready(Ok(self.state.reserved(reserve_size)))
+    }
+
+    /// Polls the fetch in progress, reading from the inner file(s) straight into the buffer.
+    fn poll_fetch(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let Some(offset) = self.state.pending_offset() else {
+            return Poll::Ready(Ok(()));
+        };
+
+        // an empty fetch only resolves a pending seek, the inner reader is left untouched
+        let is_empty = self.state.pending().is_some_and(|fetch| fetch.len() == 0);
+        if !is_empty && self.state.inner_offset != offset {
+            if self.seeking != Some(offset) {
+                // a seek for a dropped fetch has to be completed first
+                if self.seeking.is_some() {
+                    let _ = ready!(Pin::new(&mut self.file).poll_complete(cx));
+                    self.seeking = None;
+                }
+
+                self.state.stats.seeks += 1;
+                if let Err(err) = Pin::new(&mut self.file).start_seek(SeekFrom::Start(offset)) {
+                    self.state.abort();
+                    return Poll::Ready(Err(err));
+                }
+                self.seeking = Some(offset);
+            }
+
+            let result = ready!(Pin::new(&mut self.file).poll_complete(cx));
+            self.seeking = None;
+            match result {
+                Ok(position) => self.state.inner_offset = position,
+                Err(err) => {
+                    self.state.abort();
+                    return Poll::Ready(Err(err));
+                }
+            }
+        }
+
+        while let Some(space) = self.state.pending_space() {
+            let mut buf = ReadBuf::new(space);
+            let result = ready!(Pin::new(&mut self.file).poll_read(cx, &mut buf));
+            let result = result.map(|()| buf.filled().len());
+            self.state.stats.reads += 1;
+            match result {
+                Ok(0) => break,
+                Ok(read) => self.state.advance(read),
+                Err(err) => {
+                    self.state.abort();
+                    return Poll::Ready(Err(err));
+                }
+            }
+        }
+        self.state.finish();
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncRead for AsyncExactReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let size = ready!(this.poll_reserve(cx, buf.remaining()))?;
+
+        let (head, tail) = this.state.unread(size);
+        buf.put_slice(head);
+        buf.put_slice(tail);
+        this.state.consume(size);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncBufRead for AsyncExactReader<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<&[u8]>> {
+        let this = self.get_mut();
+
+        // only go to the file(s) when nothing is left from the reservations
+        if this.state.is_exhausted() {
+            ready!(this.poll_reserve(cx, DEFAULT_RESERVE_SIZE))?;
+        }
+
+        Poll::Ready(Ok(this.state.unread_slices().0))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().state.consume(amt);
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncSeek for AsyncExactReader<R> {
+    /// Moves the offset, the inner file(s) are only moved by the next reservation.
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        let this = self.get_mut();
+        this.seek_result = Some(this.state.seek(position)?);
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        let this = self.get_mut();
+        let position = this.seek_result.take().unwrap_or(this.state.physical_idx());
+
+        Poll::Ready(Ok(position))
+    }
+}
+
+/// Seeks the reader and waits for the seek to complete.
+async fn seek<R: AsyncSeek + Unpin>(file: &mut R, pos: SeekFrom) -> std::io::Result<u64> {
+    Pin::new(&mut *file).start_seek(pos)?;
+    poll_fn(|cx| Pin::new(&mut *file).poll_complete(cx)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt};
+
+    fn new_file(data: Vec<u8>) -> File<Cursor<Vec<u8>>> {
+        File {
+            size: data.len() as u64,
+            file: Cursor::new(data),
+            filename: "cursor".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reserve() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut reader = AsyncExactReader::new_single(new_file(data.clone()));
+
+        assert_eq!(reader.reserve(10).await.unwrap(), 10);
+        reader.seek(SeekFrom::Start(250)).await.unwrap();
+        assert_eq!(reader.reserve(10).await.unwrap(), 6);
+
+        let (head, tail) = reader.peek(6).await.unwrap();
+        assert_eq!([head, tail].concat(), data[250..]);
+
+        reader.seek(SeekFrom::Start(4)).await.unwrap();
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, data[4..12]);
+    }
+
+    #[tokio::test]
+    async fn test_read_to_end() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut reader = AsyncExactReader::new(Cursor::new(data.clone()))
+            .await
+            .unwrap();
+        assert_eq!(reader.size(), 256);
+
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[tokio::test]
+    async fn test_multi() {
+        let a = new_file(b"first\nsec".to_vec());
+        let b = new_file(b"ond\nthird".to_vec());
+        let mut reader = AsyncExactReader::new_multi(MultiFile::new(vec![a, b]));
+
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() != 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+        assert_eq!(lines, ["first\n", "second\n", "third"]);
+
+        reader.seek(SeekFrom::Start(7)).await.unwrap();
+        let mut buf = [0u8; 6];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"econd\n");
+
+        // seeks are lazy, reading past the end gives nothing
+        assert_eq!(reader.seek(SeekFrom::Start(30)).await.unwrap(), 30);
+        assert_eq!(reader.read(&mut buf).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,607 @@
+use std::{
+    io::SeekFrom,
+    ops::{Range, RangeInclusive},
+};
+
+use crate::{
+    cache::RangeCache,
+    stats::Stats,
+    utils::{calculate_seek, range_difference},
+    vec_deq::VecDeque,
+};
+
+/// The reservation size used when the buffer is exhausted.
+pub const DEFAULT_RESERVE_SIZE: usize = 8 * 1024;
+
+/// A read of the inner file(s) needed to serve a reservation.
+#[derive(Clone, Copy)]
+pub enum Fetch {
+    /// Reads the gap right before the buffer.
+    Front { offset: u64, len: usize },
+    /// Reads a new buffer, the current one is moved to the cache.
+    Fresh { offset: u64, len: usize },
+    /// Reads the gap right after the buffer.
+    Back { offset: u64, len: usize },
+}
+
+impl Fetch {
+    /// The physical offset the read starts from.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        match *self {
+            Fetch::Front { offset, .. }
+            | Fetch::Fresh { offset, .. }
+            | Fetch::Back { offset, .. } => offset,
+        }
+    }
+
+    /// The number of bytes to read.
+    #[inline]
+    pub fn len(&self) -> usize {
+        match *self {
+            Fetch::Front { len, .. } | Fetch::Fresh { len, .. } | Fetch::Back { len, .. } => len,
+        }
+    }
+}
+
+/// A fetch in progress.
+struct Pending {
+    fetch: Fetch,
+    /// The number of bytes read so far.
+    filled: usize,
+}
+
+/// The `BufferState` struct holds the reservation logic of the readers, leaving the I/O to them.
+///
+/// A reservation is served by at most two fetches: `seek_fetch` resolves a pending seek,
+/// then `back_fetch` reads what is missing after the buffer. Each fetch is started with `begin`,
+/// read into `pending_space` piece by piece, and completed with `finish`.
+pub struct BufferState {
+    /// The active range of file offsets within the buffer.
+    pub file_offset_view: RangeInclusive<u64>,
+
+    /// The size of the file(s).
+    pub size: u64,
+
+    /// The buffer used for caching data read from the file(s).
+    pub buffer: VecDeque<u8>,
+    /// The offset within the buffer
+    pub buffer_offset: usize,
+
+    /// Seek position to be used on `reserve`
+    pub seeked: Option<u64>,
+    /// The current position of the inner reader.
+    pub inner_offset: u64,
+
+    /// The soft limit of the buffer size, consumed bytes are evicted to stay below it.
+    pub max_buffer_size: Option<usize>,
+    /// The number of consumed bytes to keep in the buffer for seeking back.
+    pub backlog: usize,
+
+    /// The ranges swapped out of the buffer by seeks.
+    pub cache: RangeCache,
+
+    /// The I/O counters.
+    pub stats: Stats,
+
+    /// The buffer a fresh fetch is read into, before it replaces `buffer`.
+    fresh: VecDeque<u8>,
+    /// The fetch in progress.
+    pending: Option<Pending>,
+}
+
+impl BufferState {
+    /// Creates a new `BufferState` with a known size, the inner reader must be at `offset`.
+    pub fn new(size: u64, offset: u64) -> Self {
+        Self {
+            file_offset_view: offset..=offset,
+            size,
+            buffer: VecDeque::new(),
+            buffer_offset: 0,
+            seeked: None,
+            inner_offset: offset,
+            max_buffer_size: None,
+            backlog: 0,
+            cache: RangeCache::new(),
+            stats: Stats::default(),
+            fresh: VecDeque::new(),
+            pending: None,
+        }
+    }
+
+    /// Calculates the physical index within the file(s) from the current buffer offset.
+    #[inline]
+    pub fn physical_idx(&self) -> u64 {
+        self.seeked
+            .unwrap_or(self.file_offset_view.start() + self.buffer_offset as u64)
+    }
+
+    /// Drops all cached data for a new size, keeping the current offset.
+    ///
+    /// The position of the inner reader is considered unknown afterwards.
+    pub fn reset(&mut self, size: u64) {
+        let position = self.physical_idx();
+        self.pending = None;
+
+        self.size = size;
+        self.buffer.clear();
+        self.cache.clear();
+        self.file_offset_view = position..=position;
+        self.buffer_offset = 0;
+        self.seeked = None;
+        self.inner_offset = u64::MAX;
+    }
+
+    /// Moves the offset, the inner reader is left to the next reservation.
+    pub fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let calculated_seek = calculate_seek(self.size, self.physical_idx(), pos)?;
+        self.abort();
+
+        if self.file_offset_view.contains(&calculated_seek) {
+            self.buffer_offset = (calculated_seek - self.file_offset_view.start()) as usize;
+            self.seeked = None;
+            self.stats.seek_hits += 1;
+        } else {
+            self.seeked = Some(calculated_seek);
+            if self.cache.contains(calculated_seek) {
+                self.stats.cache_hits += 1;
+            } else {
+                self.stats.seek_misses += 1;
+            }
+        }
+
+        Ok(calculated_seek)
+    }
+
+    /// Resolves the pending seek from the buffer or the cached ranges, without any I/O.
+    ///
+    /// Returns whether the seek was resolved.
+    fn resolve_seek(&mut self, seek_head: u64) -> bool {
+        let real_head = *self.file_offset_view.start();
+
+        if self.file_offset_view.contains(&seek_head) {
+            self.buffer_offset = (seek_head - real_head) as usize;
+            self.seeked = None;
+            return true;
+        }
+
+        if let Some(range) = self.cache.take(seek_head) {
+            let buffer = std::mem::replace(&mut self.buffer, range.data);
+            self.cache.insert(real_head, buffer);
+
+            self.file_offset_view = range.offset..=range.offset + self.buffer.len() as u64;
+            self.buffer_offset = (seek_head - range.offset) as usize;
+            self.seeked = None;
+            return true;
+        }
+
+        false
+    }
+
+    /// Resolves the pending seek for a reservation, returning the fetch it still needs.
+    pub fn seek_fetch(&mut self, reserve_size: usize) -> Option<Fetch> {
+        let seek_head = self.seeked?;
+        let seek_tail = seek_head.saturating_add(reserve_size as u64);
+        let real_head = *self.file_offset_view.start();
+
+        if self.resolve_seek(seek_head) {
+            return None;
+        }
+
+        // a buffer starting past the end of the file(s) holds nothing to extend
+        if seek_head < real_head && seek_tail >= real_head && real_head <= self.size {
+            // only the gap before the buffer is read here, the one after it is read by `back_fetch`
+            let (front_gap, _) = range_difference(
+                seek_head..seek_tail,
+                real_head..*self.file_offset_view.end(),
+            );
+
+            // the bytes after the reservation are the least likely to be read
+            if let Some(max_buffer_size) = self.max_buffer_size {
+                let len = self.buffer.len() + (front_gap.end - front_gap.start) as usize;
+                let overflow = len.saturating_sub(max_buffer_size);
+                let unreserved = len.saturating_sub(reserve_size);
+                let trim = overflow.min(unreserved).min(self.buffer.len());
+
+                self.buffer.clear_back(trim);
+                self.file_offset_view = real_head..=self.file_offset_view.end() - trim as u64;
+            }
+
+            return Some(Fetch::Front {
+                offset: seek_head,
+                len: (front_gap.end - front_gap.start) as usize,
+            });
+        }
+
+        // nothing is requested past the end of the file(s)
+        Some(Fetch::Fresh {
+            offset: seek_head,
+            len: self.size.saturating_sub(seek_head).min(reserve_size as u64) as usize,
+        })
+    }
+
+    /// Resolves the pending seek for a backward reservation, returning the fetch it still needs.
+    ///
+    /// When the reservation reaches the buffer, only the gap after the buffer is read,
+    /// so the buffer is kept. Nothing is fetched for an empty reservation.
+    pub fn seek_fetch_back(&mut self, reserve_size: usize) -> Option<Fetch> {
+        let seek_head = self.seeked?;
+        if self.resolve_seek(seek_head) || seek_head > self.size {
+            return None;
+        }
+
+        let seek_start = seek_head.saturating_sub(reserve_size as u64);
+        let real_tail = *self.file_offset_view.end();
+        if (seek_start..seek_head).contains(&real_tail) {
+            return Some(Fetch::Back {
+                offset: real_tail,
+                len: (seek_head - real_tail) as usize,
+            });
+        }
+
+        let len = (seek_head - seek_start) as usize;
+        (len != 0).then_some(Fetch::Fresh {
+            offset: seek_start,
+            len,
+        })
+    }
+
+    /// Returns the fetch needed after the buffer for a reservation,
+    /// evicting consumed bytes to make room for it.
+    pub fn back_fetch(&mut self, reserve_size: usize) -> Option<Fetch> {
+        let head = self.physical_idx();
+        let tail = head
+            .saturating_add(reserve_size as u64)
+            .min(self.size)
+            .max(head);
+        let (_, back_gap) = range_difference(
+            head..tail,
+            *self.file_offset_view.start()..*self.file_offset_view.end(),
+        );
+        if back_gap.is_empty() {
+            return None;
+        }
+
+        let len = (back_gap.end - back_gap.start) as usize;
+        self.evict(len);
+
+        Some(Fetch::Back {
+            offset: back_gap.start,
+            len,
+        })
+    }
+
+    /// Returns the fetch needed before the current offset for a backward reservation,
+    /// trimming unread bytes from the back to stay within the maximum buffer size.
+    ///
+    /// The pending seek must be resolved by `seek_fetch_back` first.
+    pub fn front_fetch(&mut self, reserve_size: usize) -> Option<Fetch> {
+        if self.seeked.is_some() {
+            return None;
+        }
+
+        let head = self.physical_idx();
+        let (front_gap, _) = range_difference(
+            head.saturating_sub(reserve_size as u64)..head,
+            *self.file_offset_view.start()..*self.file_offset_view.end(),
+        );
+        if front_gap.is_empty() {
+            return None;
+        }
+        let len = (front_gap.end - front_gap.start) as usize;
+
+        // the bytes after the cursor are the least likely to be read when reading backward
+        if let Some(max_buffer_size) = self.max_buffer_size {
+            let overflow = (self.buffer.len() + len).saturating_sub(max_buffer_size);
+            let trim = overflow.min(self.buffer.len() - self.buffer_offset);
+
+            self.buffer.clear_back(trim);
+            self.file_offset_view =
+                *self.file_offset_view.start()..=self.file_offset_view.end() - trim as u64;
+        }
+
+        Some(Fetch::Front {
+            offset: front_gap.start,
+            len,
+        })
+    }
+
+    /// Starts the fetch, reserving zeroed space for it.
+    pub fn begin(&mut self, fetch: Fetch) {
+        match fetch {
+            Fetch::Front { len, .. } => {
+                self.buffer.spare_front(len);
+            }
+            Fetch::Fresh { len, .. } => {
+                self.fresh.clear();
+                self.fresh.spare_back(len);
+            }
+            Fetch::Back { len, .. } => {
+                self.buffer.spare_back(len);
+            }
+        }
+
+        self.pending = Some(Pending { fetch, filled: 0 });
+        self.stats.bytes_requested += fetch.len() as u64;
+    }
+
+    /// The fetch in progress.
+    #[inline]
+    #[cfg(feature = "tokio")]
+    pub fn pending(&self) -> Option<Fetch> {
+        self.pending.as_ref().map(|pending| pending.fetch)
+    }
+
+    /// The physical offset the fetch in progress continues from.
+    #[inline]
+    #[cfg(feature = "tokio")]
+    pub fn pending_offset(&self) -> Option<u64> {
+        self.pending
+            .as_ref()
+            .map(|pending| pending.fetch.offset() + pending.filled as u64)
+    }
+
+    /// The space left to read into for the fetch in progress, `None` once it is full.
+    pub fn pending_space(&mut self) -> Option<&mut [u8]> {
+        let Pending { fetch, filled } = self.pending.as_ref()?;
+        let (fetch, filled) = (*fetch, *filled);
+        if filled == fetch.len() {
+            return None;
+        }
+
+        // SAFETY: the space was zeroed by `begin`, and the buffers are left untouched until `finish`
+        let (first, second) = unsafe {
+            match fetch {
+                Fetch::Front { len, .. } => self.buffer.spare_front_unzeroed(len),
+                Fetch::Fresh { len, .. } => self.fresh.spare_back_unzeroed(len),
+                Fetch::Back { len, .. } => self.buffer.spare_back_unzeroed(len),
+            }
+        };
+
+        if filled < first.len() {
+            Some(&mut first[filled..])
+        } else {
+            Some(&mut second[filled - first.len()..])
+        }
+    }
+
+    /// Records `read` more bytes read into the space of the fetch in progress.
+    #[inline]
+    pub fn advance(&mut self, read: usize) {
+        if let Some(pending) = self.pending.as_mut() {
+            pending.filled += read;
+            self.inner_offset += read as u64;
+            self.stats.bytes_read += read as u64;
+        }
+    }
+
+    /// Completes the fetch in progress with the bytes read so far.
+    pub fn finish(&mut self) {
+        let Some(Pending { fetch, filled }) = self.pending.take() else {
+            return;
+        };
+
+        match fetch {
+            Fetch::Front { offset, len } => {
+                let cursor = self.physical_idx();
+
+                // SAFETY: the space is zeroed by `begin`, and the unfilled part is dropped below
+                unsafe { self.buffer.commit_front(len) };
+
+                // on a short read the cached data is not contiguous anymore
+                if filled != len {
+                    self.buffer.clear_back(self.buffer.len() - filled);
+                }
+
+                // the cursor is either the seek target or within the buffer for a backward reservation
+                self.file_offset_view = offset..=offset + self.buffer.len() as u64;
+                self.resolve_cursor(cursor);
+            }
+            Fetch::Fresh { offset, .. } => {
+                let cursor = self.physical_idx();
+
+                // SAFETY: the first `filled` bytes of the space are read into
+                unsafe { self.fresh.commit_back(filled) };
+
+                let fresh = std::mem::replace(&mut self.fresh, VecDeque::new());
+                let buffer = std::mem::replace(&mut self.buffer, fresh);
+                self.cache.insert(*self.file_offset_view.start(), buffer);
+
+                // the cursor is at the start of the buffer, or at its end for a backward reservation
+                self.file_offset_view = offset..=offset + self.buffer.len() as u64;
+                self.stats.buffer_clears += 1;
+                self.resolve_cursor(cursor);
+            }
+            Fetch::Back { .. } => {
+                let cursor = self.physical_idx();
+
+                // SAFETY: the first `filled` bytes of the space are read into
+                unsafe { self.buffer.commit_back(filled) };
+
+                // a pending seek is resolved once the gap up to it is read
+                self.file_offset_view =
+                    *self.file_offset_view.start()..=self.file_offset_view.end() + filled as u64;
+                if self.seeked.is_some() && self.file_offset_view.contains(&cursor) {
+                    self.resolve_cursor(cursor);
+                }
+            }
+        }
+    }
+
+    /// Moves the buffer offset to the cursor when the buffer holds it, leaving a pending seek otherwise.
+    fn resolve_cursor(&mut self, cursor: u64) {
+        let real_head = *self.file_offset_view.start();
+        if self.file_offset_view.contains(&cursor) {
+            self.buffer_offset = (cursor - real_head) as usize;
+            self.seeked = None;
+        } else {
+            self.buffer_offset = 0;
+            self.seeked = Some(cursor);
+        }
+    }
+
+    /// Drops the fetch in progress, after a failure of the inner reader.
+    pub fn abort(&mut self) {
+        if self.pending.take().is_some() {
+            // the position of the inner reader is unknown after a failed read
+            self.inner_offset = u64::MAX;
+        }
+    }
+
+    /// The number of bytes of a reservation that are cached from the current offset.
+    #[inline]
+    pub fn reserved(&self, reserve_size: usize) -> usize {
+        reserve_size.min(self.buffer.len() - self.buffer_offset)
+    }
+
+    /// The number of bytes of a backward reservation that are cached before the current offset.
+    #[inline]
+    pub fn reserved_back(&self, reserve_size: usize) -> usize {
+        match self.seeked {
+            Some(_) => 0,
+            None => reserve_size.min(self.buffer_offset),
+        }
+    }
+
+    /// Checks whether nothing is left from the reservations at the current offset.
+    #[inline]
+    pub fn is_exhausted(&self) -> bool {
+        self.seeked.is_some() || self.buffer_offset == self.buffer.len()
+    }
+
+    /// The cached data from the current buffer offset, split as in `VecDeque::as_slices`.
+    #[inline]
+    pub fn unread_slices(&self) -> (&[u8], &[u8]) {
+        let (head, tail) = self.buffer.as_slices();
+        if self.buffer_offset < head.len() {
+            (&head[self.buffer_offset..], tail)
+        } else {
+            (&tail[self.buffer_offset - head.len()..], &[])
+        }
+    }
+
+    /// The cached data within the range of buffer indices, split as in `VecDeque::as_slices`.
+    pub fn slices(&self, range: Range<usize>) -> (&[u8], &[u8]) {
+        let (head, tail) = self.buffer.as_slices();
+        if range.end <= head.len() {
+            (&head[range], &[])
+        } else if range.start >= head.len() {
+            (&tail[range.start - head.len()..range.end - head.len()], &[])
+        } else {
+            (&head[range.start..], &tail[..range.end - head.len()])
+        }
+    }
+
+    /// The first `size` bytes of the cached data from the current buffer offset.
+    pub fn unread(&self, size: usize) -> (&[u8], &[u8]) {
+        let (head, tail) = self.unread_slices();
+
+        if head.len() >= size {
+            (&head[..size], &[])
+        } else {
+            (head, &tail[..size - head.len()])
+        }
+    }
+
+    /// The first `size` bytes of the cached data from the current buffer offset, as a single slice.
+    ///
+    /// The buffer is rearranged only when the data wraps around the ring buffer.
+    pub fn unread_contiguous(&mut self, size: usize) -> &[u8] {
+        if self.unread_slices().0.len() < size {
+            self.buffer.make_contiguous();
+        }
+
+        &self.unread_slices().0[..size]
+    }
+
+    /// Copies the cached data from the current buffer offset into `buf`, advancing the offset.
+    pub fn read_buffered(&mut self, buf: &mut [u8]) -> usize {
+        let size = self.reserved(buf.len());
+        if size == 0 {
+            return 0;
+        }
+
+        copy_from(&self.buffer, self.buffer_offset, &mut buf[..size]);
+        self.buffer_offset += size;
+        self.stats.bytes_served += size as u64;
+
+        size
+    }
+
+    /// Copies the data cached at the given physical offset into `buf`, leaving the offset untouched.
+    ///
+    /// The buffer is looked up first, then the cached ranges. Returns `None` if neither holds the offset.
+    pub fn read_cached_at(&mut self, offset: u64, buf: &mut [u8]) -> Option<usize> {
+        let start = *self.file_offset_view.start();
+        let (start, data) = if start <= offset && offset < *self.file_offset_view.end() {
+            (start, &self.buffer)
+        } else {
+            let range = self.cache.get(offset)?;
+            (range.offset, &range.data)
+        };
+
+        let idx = (offset - start) as usize;
+        let size = buf.len().min(data.len() - idx);
+        copy_from(data, idx, &mut buf[..size]);
+        self.stats.bytes_served += size as u64;
+
+        Some(size)
+    }
+
+    /// Advances the offset within the cached data.
+    #[inline]
+    pub fn consume(&mut self, amt: usize) {
+        let amt = amt.min(self.buffer.len() - self.buffer_offset);
+        self.buffer_offset += amt;
+        self.stats.bytes_served += amt as u64;
+    }
+
+    /// Moves the offset back within the cached data.
+    #[inline]
+    pub fn consume_back(&mut self, amt: usize) {
+        let amt = amt.min(self.buffer_offset);
+        self.buffer_offset -= amt;
+        self.stats.bytes_served += amt as u64;
+    }
+
+    /// Drops consumed bytes from the front of the buffer,
+    /// so that `incoming` more bytes fit within the maximum buffer size.
+    fn evict(&mut self, incoming: usize) {
+        let Some(max_buffer_size) = self.max_buffer_size else {
+            return;
+        };
+
+        let overflow = (self.buffer.len() + incoming).saturating_sub(max_buffer_size);
+        let evict = overflow.min(self.buffer_offset.saturating_sub(self.backlog));
+        if evict == 0 {
+            return;
+        }
+
+        self.buffer.clear_front(evict);
+        self.buffer_offset -= evict;
+        self.file_offset_view =
+            self.file_offset_view.start() + evict as u64..=*self.file_offset_view.end();
+    }
+}
+
+/// Copies `buf.len()` bytes of the ring buffer from the index `start` into `buf`.
+fn copy_from(data: &VecDeque<u8>, start: usize, buf: &mut [u8]) {
+    let size = buf.len();
+    let (head, tail) = data.as_slices();
+    let head_len = head.len();
+    let adjusted_head_len = head_len.saturating_sub(start);
+    let tail_offset = start.saturating_sub(head_len);
+
+    if adjusted_head_len == 0 {
+        // The start is in the tail slice
+        buf.copy_from_slice(&tail[tail_offset..tail_offset + size]);
+    } else if adjusted_head_len >= size {
+        // The data is entirely in the head slice
+        buf.copy_from_slice(&head[start..start + size]);
+    } else {
+        // Data spans both head and tail slices
+        buf[..adjusted_head_len].copy_from_slice(&head[start..]);
+        buf[adjusted_head_len..]
+            .copy_from_slice(&tail[tail_offset..tail_offset + size - adjusted_head_len]);
+    }
+}
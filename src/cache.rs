@@ -0,0 +1,174 @@
+use crate::vec_deq::VecDeque;
+
+/// A range of the file(s) cached outside of the active buffer.
+pub struct CachedRange {
+    /// The physical offset of the first cached byte.
+    pub offset: u64,
+    /// The cached data.
+    pub data: VecDeque<u8>,
+}
+
+impl CachedRange {
+    /// Checks whether the offset is within the range, including its end.
+    #[inline]
+    fn contains(&self, offset: u64) -> bool {
+        (self.offset..=self.end()).contains(&offset)
+    }
+
+    /// Checks whether the range shares any byte with the given one.
+    #[inline]
+    fn overlaps(&self, offset: u64, len: usize) -> bool {
+        self.offset < offset + len as u64 && offset < self.end()
+    }
+
+    /// The physical offset right after the last cached byte.
+    #[inline]
+    fn end(&self) -> u64 {
+        self.offset + self.data.len() as u64
+    }
+}
+
+/// The `RangeCache` keeps disjoint ranges of the file(s) that were
+/// swapped out of the active buffer, evicting the least recently used ones.
+pub struct RangeCache {
+    /// The cached ranges, from the least to the most recently used.
+    ranges: Vec<CachedRange>,
+    /// The total number of cached bytes.
+    size: usize,
+
+    /// The maximum number of cached ranges.
+    max_ranges: usize,
+    /// The maximum number of cached bytes.
+    max_size: usize,
+}
+
+impl RangeCache {
+    /// Creates a new `RangeCache` that caches nothing.
+    pub const fn new() -> Self {
+        Self {
+            ranges: Vec::new(),
+            size: 0,
+            max_ranges: 0,
+            max_size: 0,
+        }
+    }
+
+    /// Updates the limits, evicting ranges if needed.
+    pub fn set_limits(&mut self, max_ranges: usize, max_size: usize) {
+        self.max_ranges = max_ranges;
+        self.max_size = max_size;
+        self.shrink(0);
+    }
+
+    /// Drops all cached ranges.
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+        self.size = 0;
+    }
+
+    /// Checks whether any cached range contains the offset.
+    pub fn contains(&self, offset: u64) -> bool {
+        self.ranges.iter().any(|range| range.contains(offset))
+    }
+
+    /// Caches the given range as the most recently used one.
+    ///
+    /// Older ranges overlapping it are dropped, as the new data is more recent.
+    pub fn insert(&mut self, offset: u64, data: VecDeque<u8>) {
+        let len = data.len();
+        if len == 0 || self.max_ranges == 0 || len > self.max_size {
+            return;
+        }
+
+        let mut size = self.size;
+        self.ranges.retain(|range| {
+            let keep = !range.overlaps(offset, len);
+            if !keep {
+                size -= range.data.len();
+            }
+            keep
+        });
+        self.size = size;
+
+        self.shrink(len);
+        self.size += len;
+        self.ranges.push(CachedRange { offset, data });
+    }
+
+    /// Returns the range holding the byte at the offset, leaving its place in the LRU order.
+    pub fn get(&self, offset: u64) -> Option<&CachedRange> {
+        self.ranges
+            .iter()
+            .find(|range| range.offset <= offset && offset < range.end())
+    }
+
+    /// Takes the range containing the offset out of the cache.
+    pub fn take(&mut self, offset: u64) -> Option<CachedRange> {
+        let idx = self
+            .ranges
+            .iter()
+            .position(|range| range.contains(offset))?;
+        let range = self.ranges.remove(idx);
+        self.size -= range.data.len();
+
+        Some(range)
+    }
+
+    /// Evicts the least recently used ranges until `incoming` more bytes and one more range fit.
+    fn shrink(&mut self, incoming: usize) {
+        let max_ranges = self.max_ranges.saturating_sub((incoming > 0) as usize);
+        let mut evict = 0;
+
+        while self.ranges.len() - evict > max_ranges || self.size + incoming > self.max_size {
+            if evict == self.ranges.len() {
+                break;
+            }
+            self.size -= self.ranges[evict].data.len();
+            evict += 1;
+        }
+
+        self.ranges.drain(..evict);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(len: usize) -> VecDeque<u8> {
+        let mut data = VecDeque::new();
+        data.extend_back(&vec![0u8; len]);
+        data
+    }
+
+    #[test]
+    fn test_lru() {
+        let mut cache = RangeCache::new();
+        cache.set_limits(2, 100);
+
+        cache.insert(0, data(10));
+        cache.insert(20, data(10));
+        cache.insert(40, data(10));
+
+        assert!(!cache.contains(5));
+        assert!(cache.contains(25));
+        assert!(cache.contains(50));
+
+        cache.insert(60, data(95));
+        assert!(!cache.contains(25));
+        assert!(!cache.contains(45));
+        assert!(cache.contains(60));
+    }
+
+    #[test]
+    fn test_overlap() {
+        let mut cache = RangeCache::new();
+        cache.set_limits(4, 100);
+
+        cache.insert(0, data(10));
+        cache.insert(5, data(10));
+        assert!(!cache.contains(2));
+        assert_eq!(cache.take(7).unwrap().offset, 5);
+        assert!(cache.take(7).is_none());
+    }
+}
@@ -1,10 +1,28 @@
 #![doc = include_str!("../README.md")]
 
+mod buffer;
+mod cache;
 mod utils;
 mod vec_deq;
 
+#[cfg(feature = "tokio")]
+mod async_reader;
+#[cfg(feature = "mmap")]
+mod mmap;
 mod multifile;
+mod prefetch;
+mod read_at;
 mod reader;
+mod shared;
+mod stats;
 
-pub use multifile::{File, MultiFile};
+#[cfg(feature = "tokio")]
+pub use async_reader::AsyncExactReader;
+#[cfg(feature = "mmap")]
+pub use mmap::{MmapFile, MmapReader};
+pub use multifile::{File, LazyFile, MultiFile, Segment};
+pub use prefetch::Prefetcher;
+pub use read_at::ReadAt;
 pub use reader::ExactReader;
+pub use shared::SharedExactReader;
+pub use stats::{FileStats, Stats};
@@ -0,0 +1,428 @@
+use std::{
+    io::{BufRead, Read, Seek, SeekFrom},
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use memmap2::Mmap;
+
+use crate::{
+    multifile::{File, MultiFile},
+    read_at::ReadAt,
+    utils::calculate_seek,
+};
+
+/// The `MmapFile` struct represents a file mapped into memory.
+///
+/// It implements `Read` and `Seek`, so it can be the inner reader of a [`File`],
+/// and in turn of a `MultiFile` or an `ExactReader`.
+///
+/// # Truncation
+///
+/// Accessing the mapping past the end of a truncated file raises `SIGBUS`.
+/// The length of the file is checked once per read, positional or not,
+/// and by [`MmapReader::try_reserve`], so a truncation before the check only shortens the data.
+/// A truncation after the check is undefined behavior, which is why mapping is `unsafe`.
+pub struct MmapFile {
+    /// The opened file, kept to check its length.
+    file: std::fs::File,
+    /// The mapping, `None` for a zero-length file.
+    map: Option<Mmap>,
+    /// The mapped length still backed by the file, as of the last check.
+    valid_len: AtomicU64,
+    /// The position within the file.
+    position: u64,
+}
+
+impl MmapFile {
+    /// Opens and maps the file at the given path.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated, by this or another process, while the mapped data
+    /// is accessed after the last length check, see the truncation hazard of `MmapFile`.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+
+        // zero-length files cannot be mapped
+        let map = if file.metadata()?.len() == 0 {
+            None
+        } else {
+            // SAFETY: the caller ensures the file is not truncated while it is accessed
+            Some(unsafe { Mmap::map(&file)? })
+        };
+        let valid_len = map.as_ref().map_or(0, |map| map.len() as u64);
+
+        Ok(Self {
+            file,
+            map,
+            valid_len: AtomicU64::new(valid_len),
+            position: 0,
+        })
+    }
+
+    /// The size of the file in bytes when it was mapped.
+    pub fn size(&self) -> u64 {
+        self.map.as_ref().map_or(0, |map| map.len() as u64)
+    }
+
+    /// Updates the mapped length still backed by the file from its current length.
+    fn check_len(&self) {
+        if self.map.is_some() {
+            let len = self.file.metadata().map_or(0, |meta| meta.len());
+            self.valid_len
+                .store(len.min(self.size()), Ordering::Relaxed);
+        }
+    }
+
+    /// The mapped bytes that were backed by the file at the last check.
+    fn data(&self) -> &[u8] {
+        let Some(map) = self.map.as_ref() else {
+            return &[];
+        };
+
+        &map[..self.valid_len.load(Ordering::Relaxed) as usize]
+    }
+}
+
+impl Read for MmapFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.read_at(self.position, buf)?;
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl Seek for MmapFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = calculate_seek(self.size(), self.position, pos)?;
+        Ok(self.position)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.position)
+    }
+}
+
+/// Copies from the mapping, after checking the length of the file.
+impl ReadAt for MmapFile {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.check_len();
+        let data = self.data();
+        // the offset within a mapped file always fits in memory
+        let data = &data[offset.min(data.len() as u64) as usize..];
+
+        let size = data.len().min(buf.len());
+        buf[..size].copy_from_slice(&data[..size]);
+        Ok(size)
+    }
+}
+
+impl File<MmapFile> {
+    /// Opens and maps the file at the given path.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`MmapFile::open`].
+    pub unsafe fn open_mmap<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let file = MmapFile::open(path)?;
+
+        Ok(Self {
+            size: file.size(),
+            file,
+            filename: path.to_string_lossy().into_owned(),
+        })
+    }
+}
+
+/// The `MmapReader` struct is the memory-mapped counterpart of `ExactReader`.
+///
+/// The data is served straight from the mappings: reservations, peeks and `fill_buf`
+/// do no I/O, and peeks only copy when they span more than two files.
+///
+/// The lengths of the files are checked by reads and by [`MmapReader::try_reserve`],
+/// the other accesses rely on the last check.
+pub struct MmapReader {
+    /// The mapped files.
+    files: MultiFile<MmapFile>,
+    /// The current position.
+    position: u64,
+    /// The buffer for the data spanning more than two files.
+    scratch: Vec<u8>,
+}
+
+impl MmapReader {
+    /// Creates a new `MmapReader` instance for reading data from a single file.
+    pub fn new_single(file: File<MmapFile>) -> Self {
+        Self::new_multi(MultiFile::new(vec![file]))
+    }
+
+    /// Creates a new `MmapReader` instance for reading data from multiple files,
+    /// as if they were concatenated.
+    ///
+    /// Only the files and their ranges are used, the cursor of the `MultiFile` is ignored.
+    pub fn new_multi(files: MultiFile<MmapFile>) -> Self {
+        Self {
+            files,
+            position: 0,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// The mapped files, to map the offsets of the reader to files with [`MultiFile::locate`].
+    pub fn files(&self) -> &MultiFile<MmapFile> {
+        &self.files
+    }
+
+    /// The total size of the file(s) in bytes.
+    pub fn size(&self) -> u64 {
+        self.files.size()
+    }
+
+    /// Checks the length of the files holding the next `size` bytes.
+    fn check_lens(&self, size: usize) {
+        let end = self.position.saturating_add(size as u64);
+        let Some((first, _, _)) = self.files.locate(self.position) else {
+            return;
+        };
+
+        self.files
+            .segments()
+            .skip(first)
+            .take_while(|segment| segment.range.start < end)
+            .for_each(|segment| segment.file.file.check_len());
+    }
+
+    /// The contiguous data from `offset` to the end of its file, empty at the end of the file(s).
+    fn slice_at(&self, offset: u64) -> &[u8] {
+        // the lookup skips the zero-length files
+        let Some(segment) = self
+            .files
+            .locate(offset)
+            .and_then(|(idx, _, _)| self.files.segment(idx))
+        else {
+            return &[];
+        };
+
+        let data = segment.file.file.data();
+        // the offset within a mapped file always fits in memory
+        let infile = (offset - segment.range.start).min(data.len() as u64) as usize;
+        &data[infile..]
+    }
+
+    /// Counts the bytes available from the current position, up to `reserve_size`.
+    fn available(&self, reserve_size: usize) -> usize {
+        let mut available = 0;
+        while available < reserve_size {
+            let slice = self.slice_at(self.position + available as u64);
+            if slice.is_empty() {
+                break;
+            }
+            available += slice.len();
+        }
+
+        available.min(reserve_size)
+    }
+
+    /// Does nothing, the data is always available in the mappings.
+    pub fn reserve(&mut self, _reserve_size: usize) {}
+
+    /// Returns the number of bytes available from the current offset, up to `reserve_size`.
+    ///
+    /// The returned value is less than `reserve_size` only when the end of the file(s) is reached,
+    /// or a file was truncated.
+    pub fn try_reserve(&mut self, reserve_size: usize) -> std::io::Result<usize> {
+        self.check_lens(reserve_size);
+        Ok(self.available(reserve_size))
+    }
+
+    /// Returns the next `peek_size` bytes without advancing the offset.
+    ///
+    /// The data is split in two slices when it spans two files,
+    /// and copied when it spans more.
+    pub fn peek(&mut self, peek_size: usize) -> std::io::Result<(&[u8], &[u8])> {
+        let head_len = self.slice_at(self.position).len();
+        if head_len >= peek_size {
+            return Ok((&self.slice_at(self.position)[..peek_size], &[]));
+        }
+
+        let tail_len = self.slice_at(self.position + head_len as u64).len();
+        if head_len + tail_len >= peek_size || self.available(peek_size) == head_len + tail_len {
+            let head = self.slice_at(self.position);
+            let tail = self.slice_at(self.position + head_len as u64);
+            return Ok((head, &tail[..tail_len.min(peek_size - head_len)]));
+        }
+
+        Ok((self.copy_scratch(peek_size), &[]))
+    }
+
+    /// Returns the next `peek_size` bytes as a single slice without advancing the offset.
+    ///
+    /// The data is copied only when it spans more than one file.
+    pub fn peek_contiguous(&mut self, peek_size: usize) -> std::io::Result<&[u8]> {
+        let head_len = self.slice_at(self.position).len();
+        if head_len >= peek_size || self.available(peek_size) == head_len {
+            let head = self.slice_at(self.position);
+            return Ok(&head[..head_len.min(peek_size)]);
+        }
+
+        Ok(self.copy_scratch(peek_size))
+    }
+
+    /// Copies up to `size` bytes from the current offset into the scratch buffer.
+    fn copy_scratch(&mut self, size: usize) -> &[u8] {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+
+        while scratch.len() < size {
+            let slice = self.slice_at(self.position + scratch.len() as u64);
+            if slice.is_empty() {
+                break;
+            }
+            scratch.extend_from_slice(&slice[..slice.len().min(size - scratch.len())]);
+        }
+
+        self.scratch = scratch;
+        &self.scratch
+    }
+}
+
+impl Read for MmapReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.check_lens(buf.len());
+
+        let mut taken = 0;
+        while taken < buf.len() {
+            let slice = self.slice_at(self.position + taken as u64);
+            if slice.is_empty() {
+                break;
+            }
+
+            let size = slice.len().min(buf.len() - taken);
+            buf[taken..taken + size].copy_from_slice(&slice[..size]);
+            taken += size;
+        }
+        self.position += taken as u64;
+
+        Ok(taken)
+    }
+}
+
+impl BufRead for MmapReader {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        Ok(self.slice_at(self.position))
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt.min(self.slice_at(self.position).len()) as u64;
+    }
+}
+
+impl Seek for MmapReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = calculate_seek(self.size(), self.position, pos)?;
+        Ok(self.position)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExactReader;
+
+    fn temp_file(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("exact_reader_mmap_{name}.bin"));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn truncate(path: &Path, len: u64) {
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_len(len)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_multi() {
+        let paths = [
+            temp_file("multi_a", &[1, 2, 3]),
+            temp_file("multi_b", &[]),
+            temp_file("multi_c", &[4, 5]),
+            temp_file("multi_d", &[6, 7, 8]),
+        ];
+        // SAFETY: the files are not truncated
+        let files = paths
+            .iter()
+            .map(|p| unsafe { File::open_mmap(p) }.unwrap())
+            .collect();
+        let mut reader = MmapReader::new_multi(MultiFile::new(files));
+        assert_eq!(reader.size(), 8);
+
+        reader.seek(SeekFrom::Start(2)).unwrap();
+        assert_eq!(reader.peek(3).unwrap(), (&[3u8][..], &[4u8, 5][..]));
+        assert_eq!(reader.peek_contiguous(4).unwrap(), [3, 4, 5, 6]);
+        assert_eq!(
+            reader.peek(10).unwrap(),
+            (&[3u8, 4, 5, 6, 7, 8][..], &[][..])
+        );
+
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, [3, 4, 5, 6, 7, 8]);
+        assert_eq!(reader.try_reserve(4).unwrap(), 0);
+
+        // the mapped files are sources of the buffered reader as well
+        let files = paths
+            .iter()
+            .map(|p| unsafe { File::open_mmap(p) }.unwrap())
+            .collect();
+        let mut reader = ExactReader::new_multi(MultiFile::new(files));
+        reader.seek(SeekFrom::Start(2)).unwrap();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, [3, 4, 5, 6, 7, 8]);
+
+        for path in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        let path = temp_file("truncated", &[1, 2, 3, 4, 5, 6, 7, 8]);
+        // SAFETY: the file is only truncated between the accesses, before the length checks
+        let mut reader = MmapReader::new_single(unsafe { File::open_mmap(&path) }.unwrap());
+
+        truncate(&path, 3);
+        assert_eq!(reader.size(), 8);
+        assert_eq!(reader.try_reserve(8).unwrap(), 3);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(reader.read(&mut buf).unwrap(), 3);
+        assert_eq!(buf[..3], [1, 2, 3]);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+        // the length is checked by each read of the file as well
+        let mut file = unsafe { MmapFile::open(&path) }.unwrap();
+        truncate(&path, 2);
+        assert_eq!(file.read(&mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [1, 2]);
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+
+        // and by positional reads
+        truncate(&path, 1);
+        assert_eq!(file.read_at(0, &mut buf).unwrap(), 1);
+        assert_eq!(file.read_at(1, &mut buf).unwrap(), 0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
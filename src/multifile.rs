@@ -1,22 +1,53 @@
-use std::io::{Read, Seek};
+use std::{
+    io::{IoSliceMut, Read, Seek},
+    ops::Range,
+    path::Path,
+};
 
-use crate::utils::calculate_seek;
+use crate::{read_at::ReadAt, stats::FileStats, utils::calculate_seek};
+
+#[cfg(feature = "tokio")]
+mod async_io;
+mod discover;
+mod lazy;
+
+pub use lazy::LazyFile;
 
 /// The `File` struct represents an individual file within the multi-file context.
 pub struct File<R> {
     /// The inner reader for the file.
     pub file: R,
     /// The size of the file in bytes.
-    pub size: usize,
+    pub size: u64,
     /// The name of the file.
     pub filename: String,
 }
 
+impl File<std::fs::File> {
+    /// Opens the file at the given path, taking the size from its metadata.
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            file,
+            size,
+            filename: path.to_string_lossy().into_owned(),
+        })
+    }
+}
+
 impl<R: Read> Read for File<R> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         self.file.read(buf)
     }
+
+    #[inline]
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<usize> {
+        self.file.read_vectored(bufs)
+    }
 }
 
 impl<R: Seek> Seek for File<R> {
@@ -26,147 +57,501 @@ impl<R: Seek> Seek for File<R> {
     }
 }
 
+impl<R: ReadAt> ReadAt for File<R> {
+    #[inline]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.file.read_at(offset, buf)
+    }
+
+    #[inline]
+    fn read_vectored_at(&self, offset: u64, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<usize> {
+        self.file.read_vectored_at(offset, bufs)
+    }
+}
+
 /// The `MultiFile` struct combines multiple files into a unified stream,
 /// allowing sequential reading as if all files are concatenated.
 pub struct MultiFile<R> {
     /// The list of files
     files: Vec<File<R>>,
+    /// The cumulative offset to the start of each file.
+    starts: Vec<u64>,
 
     /// The cumulative offset to the current file within the multi-file context.
     /// (without the in-file offset)
-    cumul_offset: usize,
+    cumul_offset: u64,
     /// The offset within the current file.
-    infile_offset: usize,
+    infile_offset: u64,
 
     /// The total size of the combined multi-file stream.
-    total_len: usize,
+    total_len: u64,
     /// The index of the current file being read from.
     current_file_idx: usize,
+
+    /// The I/O counters of each file.
+    stats: Vec<FileStats>,
+
+    /// The seek in progress through `AsyncSeek`.
+    #[cfg(feature = "tokio")]
+    pending_seek: Option<async_io::PendingSeek>,
+}
+
+/// A file of a `MultiFile`, with its place in the combined stream.
+pub struct Segment<'a, R> {
+    /// The index of the file.
+    pub idx: usize,
+    /// The range of the file within the combined stream.
+    pub range: Range<u64>,
+    /// The file.
+    pub file: &'a File<R>,
+}
+
+/// The file seeks needed to move a `MultiFile` to a new position.
+struct SeekPlan {
+    /// The index of the target file.
+    idx: usize,
+    /// The cumulative offset to the target file.
+    cumul_offset: u64,
+    /// The offset within the target file.
+    infile_offset: u64,
+
+    /// The files passed over by the seek.
+    skipped: Range<usize>,
+    /// Where the files passed over are moved to.
+    skip_to: std::io::SeekFrom,
 }
 
 impl<R> MultiFile<R> {
     /// Creates a new `MultiFile` instance with the provided list of files.
     pub fn new(files: Vec<File<R>>) -> Self {
-        let total_len = files.iter().map(|f| f.size).sum();
+        let mut total_len = 0;
+        let starts = files
+            .iter()
+            .map(|file| {
+                let start = total_len;
+                total_len += file.size;
+                start
+            })
+            .collect();
+        let stats = vec![FileStats::default(); files.len()];
         Self {
             current_file_idx: 0,
             infile_offset: 0,
             cumul_offset: 0,
             files,
+            starts,
             total_len,
+            stats,
+            #[cfg(feature = "tokio")]
+            pending_seek: None,
         }
     }
 
     /// Converts the given position within the combined multi-file stream
     /// to the index of the corresponding file within the `files`.
+    ///
+    /// The last file starting at or before the position is found by binary search,
+    /// so zero-length files are skipped, and the end of the stream maps to the last file.
+    /// Returns `None` past the end of the stream, or when there are no files.
     #[inline]
-    fn needle_to_file(&self, needle: usize) -> Option<usize> {
+    fn needle_to_file(&self, needle: u64) -> Option<usize> {
         if needle > self.total_len {
             return None;
         }
 
-        if self.cumul_offset == needle {
-            return Some(self.current_file_idx);
+        self.starts
+            .partition_point(|&start| start <= needle)
+            .checked_sub(1)
+    }
+
+    /// Plans the file seeks needed to move to the given position.
+    fn plan_seek(&self, pos: std::io::SeekFrom) -> std::io::Result<SeekPlan> {
+        let calculated_seek = calculate_seek(self.total_len, self.physical_offset(), pos)?;
+        if calculated_seek > self.total_len {
+            return Err(std::io::ErrorKind::InvalidInput.into());
         }
 
-        let mut res = if self.cumul_offset > needle {
-            0
-        } else {
-            self.cumul_offset
+        // without files, the start of the empty stream is in no file
+        let calculated_idx = self.needle_to_file(calculated_seek).unwrap_or(0);
+        let new_cum = self.starts.get(calculated_idx).copied().unwrap_or(0);
+
+        // the files in between are left where a sequential read would leave them
+        let (skipped, skip_to) = match calculated_idx.cmp(&self.current_file_idx) {
+            std::cmp::Ordering::Greater => (
+                self.current_file_idx..calculated_idx,
+                std::io::SeekFrom::End(0),
+            ),
+            std::cmp::Ordering::Less => (
+                calculated_idx + 1..self.current_file_idx + 1,
+                std::io::SeekFrom::Start(0),
+            ),
+            std::cmp::Ordering::Equal => (0..0, std::io::SeekFrom::Start(0)),
         };
 
-        for (idx, file) in self.files.iter().enumerate().take(self.current_file_idx) {
-            if res + file.size >= needle {
-                return Some(idx);
-            }
-            res += file.size;
+        Ok(SeekPlan {
+            idx: calculated_idx,
+            cumul_offset: new_cum,
+            infile_offset: calculated_seek - new_cum,
+            skipped,
+            skip_to,
+        })
+    }
+
+    /// Recomputes the offsets of the files from `idx` on, after the files were changed.
+    fn update_offsets(&mut self, idx: usize) {
+        self.starts.truncate(idx);
+        let mut total_len = idx
+            .checked_sub(1)
+            .map_or(0, |prev| self.starts[prev] + self.files[prev].size);
+        for file in &self.files[idx..] {
+            self.starts.push(total_len);
+            total_len += file.size;
         }
 
-        unreachable!()
+        self.total_len = total_len;
+        self.cumul_offset = self.starts.get(self.current_file_idx).copied().unwrap_or(0);
+
+        // the files of a seek in progress may have moved
+        #[cfg(feature = "tokio")]
+        {
+            self.pending_seek = None;
+        }
+    }
+
+    /// Appends a file at the end of the stream.
+    ///
+    /// The cursor is left untouched, so reading continues into the new file once the
+    /// previous ones are exhausted.
+    pub fn push_file(&mut self, file: File<R>) {
+        self.starts.push(self.total_len);
+        self.total_len += file.size;
+        self.files.push(file);
+        self.stats.push(FileStats::default());
+    }
+
+    /// Updates the offsets once the seeks of the plan are done,
+    /// returning the new position within the combined multi-file stream.
+    fn apply_seek(&mut self, plan: &SeekPlan, infile_offset: u64) -> u64 {
+        self.current_file_idx = plan.idx;
+        self.cumul_offset = plan.cumul_offset;
+        self.infile_offset = infile_offset;
+
+        self.cumul_offset + self.infile_offset
     }
 
     /// Calculates the physical offset within the combined multi-file stream.
     #[inline]
-    fn physical_offset(&self) -> usize {
+    fn physical_offset(&self) -> u64 {
         self.cumul_offset + self.infile_offset
     }
 
+    /// Reads up to `len` bytes from the given offset with `read`, one file at a time,
+    /// without moving the current file or the offsets within the files.
+    ///
+    /// `read` is given the file, the offset within it, the number of bytes taken so far,
+    /// and the length of the segment, which ends at the end of the file at most.
+    fn read_segments_at(
+        &self,
+        offset: u64,
+        len: usize,
+        mut read: impl FnMut(&File<R>, u64, usize, usize) -> std::io::Result<usize>,
+    ) -> std::io::Result<usize> {
+        let Some(mut idx) = self.needle_to_file(offset) else {
+            return Ok(0);
+        };
+        let mut cumul_offset = self.starts[idx];
+        let mut taken = 0;
+
+        while taken < len && idx < self.files.len() {
+            let file = &self.files[idx];
+            let infile_offset = offset + taken as u64 - cumul_offset;
+            if infile_offset >= file.size {
+                cumul_offset += file.size;
+                idx += 1;
+                continue;
+            }
+
+            // the `min` is done in `u64`, so that large files do not wrap on 32-bit targets
+            let wanted = (file.size - infile_offset).min((len - taken) as u64) as usize;
+            let read = read(file, infile_offset, taken, wanted)?;
+            if read == 0 {
+                break;
+            }
+            taken += read;
+        }
+
+        Ok(taken)
+    }
+
+    /// Reads up to `len` bytes from the current position with `read`,
+    /// which is given the current file and the number of bytes taken so far.
+    ///
+    /// A file is read until its end, even when it returns fewer bytes than requested,
+    /// and the last file is read until it returns `Ok(0)`.
+    fn read_files(
+        &mut self,
+        len: usize,
+        mut read: impl FnMut(&mut File<R>, usize) -> std::io::Result<usize>,
+    ) -> std::io::Result<usize> {
+        let mut taken = 0;
+
+        while taken < len {
+            let is_last = self.current_file_idx + 1 >= self.files.len();
+            // there may be no files, such as for a glob matching nothing or once all are removed
+            let Some(file) = self.files.get_mut(self.current_file_idx) else {
+                break;
+            };
+
+            if self.infile_offset < file.size || is_last {
+                self.stats[self.current_file_idx].reads += 1;
+                let read = read(file, taken)?;
+                if read == 0 && is_last {
+                    break;
+                }
+
+                self.infile_offset += read as u64;
+                taken += read;
+                if read > 0 && self.infile_offset < file.size {
+                    continue;
+                }
+            }
+
+            // the file is known to be exhausted, so it is not read again to find its end
+            if is_last {
+                break;
+            }
+            self.cumul_offset += file.size;
+            self.current_file_idx += 1;
+            self.infile_offset = 0;
+        }
+
+        Ok(taken)
+    }
+
     /// The total size of the multi-file stream in bytes.
-    pub fn size(&self) -> usize {
+    pub fn size(&self) -> u64 {
         self.total_len
     }
+
+    /// Maps the position within the combined stream to the index of its file,
+    /// the name of the file, and the offset within the file.
+    ///
+    /// The end of the stream maps to the end of the last file.
+    /// Returns `None` past the end of the stream.
+    pub fn locate(&self, offset: u64) -> Option<(usize, &str, u64)> {
+        let idx = self.needle_to_file(offset)?;
+        Some((idx, &self.files[idx].filename, offset - self.starts[idx]))
+    }
+
+    /// The range of the file at the given index within the combined stream.
+    pub fn segment_range(&self, idx: usize) -> Option<Range<u64>> {
+        self.segment(idx).map(|segment| segment.range)
+    }
+
+    /// The file at the given index with its range within the combined stream.
+    pub(crate) fn segment(&self, idx: usize) -> Option<Segment<'_, R>> {
+        let file = self.files.get(idx)?;
+        let start = self.starts[idx];
+
+        Some(Segment {
+            idx,
+            range: start..start + file.size,
+            file,
+        })
+    }
+
+    /// The file the cursor is in, `None` when there are no files.
+    ///
+    /// The offset within the file is the current position minus the start of its range.
+    pub fn current_segment(&self) -> Option<Segment<'_, R>> {
+        self.segment(self.current_file_idx)
+    }
+
+    /// The files with their ranges within the combined stream, in order.
+    pub fn segments(&self) -> impl ExactSizeIterator<Item = Segment<'_, R>> + '_ {
+        (0..self.files.len()).map(|idx| self.segment(idx).unwrap())
+    }
+
+    /// The I/O counters of each file, in the order of the files.
+    pub fn stats(&self) -> &[FileStats] {
+        &self.stats
+    }
+
+    /// Resets the I/O counters of all files.
+    pub fn reset_stats(&mut self) {
+        self.stats.fill(FileStats::default());
+    }
 }
 
-impl<R: Read> Read for MultiFile<R> {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let tail_idx;
-        let mut infile = 0;
+impl<R: Seek> MultiFile<R> {
+    /// Inserts a file at the given index, shifting the files after it.
+    ///
+    /// The cursor stays on the same data, so its offset in the stream moves
+    /// when the file is inserted before it. A file inserted before the cursor
+    /// is moved to its end, as if it was read through.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is greater than the number of files.
+    pub fn insert_file(&mut self, idx: usize, mut file: File<R>) -> std::io::Result<()> {
+        let before_cursor = idx <= self.current_file_idx && !self.files.is_empty();
+        if before_cursor {
+            file.seek(std::io::SeekFrom::End(0))?;
+        }
 
-        let expected = buf.len();
-        let mut taken = 0;
+        self.files.insert(idx, file);
+        self.stats.insert(idx, FileStats::default());
+        if before_cursor {
+            self.current_file_idx += 1;
+        }
 
-        'find: {
-            for (idx, file) in self.files[self.current_file_idx..].iter_mut().enumerate() {
-                infile = file.read(&mut buf[taken..])?;
-                taken += infile;
-                if taken == expected {
-                    tail_idx = self.current_file_idx + idx;
-                    break 'find;
-                }
+        self.update_offsets(idx);
+        Ok(())
+    }
+
+    /// Removes and returns the file at the given index, shifting the files after it.
+    ///
+    /// The cursor stays on the same data, unless it is in the removed file.
+    /// It then moves to the start of the next file, or to the end of the stream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of bounds.
+    pub fn remove_file(&mut self, idx: usize) -> std::io::Result<File<R>> {
+        let is_last = idx + 1 == self.files.len();
+        if idx == self.current_file_idx && is_last && idx > 0 {
+            // the previous file becomes the current one, at its end
+            self.files[idx - 1].seek(std::io::SeekFrom::End(0))?;
+        }
+
+        let file = self.files.remove(idx);
+        self.stats.remove(idx);
+
+        match idx.cmp(&self.current_file_idx) {
+            std::cmp::Ordering::Less => self.current_file_idx -= 1,
+            std::cmp::Ordering::Equal if !is_last => self.infile_offset = 0,
+            std::cmp::Ordering::Equal => {
+                self.current_file_idx = idx.saturating_sub(1);
+                self.infile_offset = self.files.last().map_or(0, |last| last.size);
             }
-            tail_idx = self.files.len() - 1;
+            std::cmp::Ordering::Greater => {}
         }
-        let _cumul_offset: usize = self.files[self.current_file_idx..tail_idx]
-            .iter()
-            .map(|f| f.size)
-            .sum();
 
-        self.cumul_offset += _cumul_offset;
-        self.current_file_idx = tail_idx;
-        self.infile_offset = infile;
+        self.update_offsets(idx.min(self.current_file_idx));
+        Ok(file)
+    }
 
-        Ok(taken)
+    /// Replaces the file at the given index, returning the previous one.
+    ///
+    /// When the cursor is in the replaced file, it keeps its offset within the file,
+    /// up to the size of the new one, and the new file is moved there.
+    /// A file replaced before the cursor is moved to its end.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of bounds.
+    pub fn replace_file(&mut self, idx: usize, mut file: File<R>) -> std::io::Result<File<R>> {
+        assert!(idx < self.files.len(), "the file index is out of bounds");
+
+        if idx < self.current_file_idx {
+            file.seek(std::io::SeekFrom::End(0))?;
+        } else if idx == self.current_file_idx {
+            let infile_offset = self.infile_offset.min(file.size);
+            file.seek(std::io::SeekFrom::Start(infile_offset))?;
+            self.infile_offset = infile_offset;
+        }
+
+        let previous = std::mem::replace(&mut self.files[idx], file);
+        self.stats[idx] = FileStats::default();
+        self.update_offsets(idx);
+
+        Ok(previous)
     }
 }
 
-impl<R: Read + Seek> Seek for MultiFile<R> {
-    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
-        let calculated_seek = calculate_seek(self.total_len, self.physical_offset(), pos)? as usize;
-        let calculated_idx = self.needle_to_file(calculated_seek).unwrap();
+impl<R: Read> Read for MultiFile<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.read_files(buf.len(), |file, taken| file.read(&mut buf[taken..]))
+    }
 
-        let new_cum = self.files[..calculated_idx]
-            .iter()
-            .map(|f| f.size)
-            .sum::<usize>();
+    /// Reads each file once with `read_vectored`, moving to the next file at its end.
+    fn read_vectored(&mut self, mut bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<usize> {
+        IoSliceMut::advance_slices(&mut bufs, 0);
+        let total = bufs.iter().map(|buf| buf.len()).sum();
+
+        self.read_files(total, |file, _| {
+            let read = file.read_vectored(bufs)?;
+            IoSliceMut::advance_slices(&mut bufs, read);
+            Ok(read)
+        })
+    }
+}
 
-        let seek_to = calculated_seek - new_cum;
+impl<R: ReadAt> ReadAt for MultiFile<R> {
+    /// Reads across the files from the given offset,
+    /// without moving the current file or the offsets within the files.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.read_segments_at(offset, buf.len(), |file, infile_offset, taken, len| {
+            file.read_at(infile_offset, &mut buf[taken..taken + len])
+        })
+    }
 
-        match calculated_idx.cmp(&self.current_file_idx) {
-            std::cmp::Ordering::Greater => {
-                for file in self.files[..calculated_idx].iter_mut() {
-                    let _ = file.seek(std::io::SeekFrom::End(0))?;
-                }
-            }
-            std::cmp::Ordering::Less => {
-                for file in self.files[calculated_idx + 1..=self.current_file_idx].iter_mut() {
-                    let _ = file.seek(std::io::SeekFrom::Start(0))?;
-                }
-            }
-            std::cmp::Ordering::Equal => {}
+    /// Reads each file once with `read_vectored_at`, in segments that end at the file boundaries.
+    fn read_vectored_at(&self, offset: u64, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<usize> {
+        let total = bufs.iter().map(|buf| buf.len()).sum();
+
+        self.read_segments_at(offset, total, |file, infile_offset, taken, len| {
+            let mut segment = sub_slices(bufs, taken, len);
+            file.read_vectored_at(infile_offset, &mut segment)
+        })
+    }
+}
+
+/// Borrows `len` bytes of the slices, starting `skip` bytes into them.
+fn sub_slices<'a>(
+    bufs: &'a mut [IoSliceMut<'_>],
+    mut skip: usize,
+    mut len: usize,
+) -> Vec<IoSliceMut<'a>> {
+    let mut slices = Vec::new();
+    for buf in bufs.iter_mut() {
+        if len == 0 {
+            break;
         }
+        if skip >= buf.len() {
+            skip -= buf.len();
+            continue;
+        }
+
+        let end = buf.len().min(skip + len);
+        slices.push(IoSliceMut::new(&mut buf[skip..end]));
+        len -= end - skip;
+        skip = 0;
+    }
 
-        let res =
-            self.files[calculated_idx].seek(std::io::SeekFrom::Start(seek_to as u64))? as usize;
+    slices
+}
+
+impl<R: Read + Seek> Seek for MultiFile<R> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        let plan = self.plan_seek(pos)?;
 
-        self.current_file_idx = calculated_idx;
-        self.cumul_offset = new_cum;
-        self.infile_offset = res;
+        for idx in plan.skipped.clone() {
+            self.stats[idx].seeks += 1;
+            let _ = self.files[idx].seek(plan.skip_to)?;
+        }
+
+        if self.files.is_empty() {
+            return Ok(self.apply_seek(&plan, 0));
+        }
 
-        Ok((new_cum + res) as u64)
+        self.stats[plan.idx].seeks += 1;
+        let res = self.files[plan.idx].seek(std::io::SeekFrom::Start(plan.infile_offset))?;
+        Ok(self.apply_seek(&plan, res))
     }
 
     fn stream_position(&mut self) -> std::io::Result<u64> {
-        Ok(self.physical_offset() as u64)
+        Ok(self.physical_offset())
     }
 }
 
@@ -177,7 +562,7 @@ mod tests {
 
     impl From<Cursor<Vec<u8>>> for File<Cursor<Vec<u8>>> {
         fn from(value: Cursor<Vec<u8>>) -> Self {
-            let len = value.get_ref().len();
+            let len = value.get_ref().len() as u64;
             Self {
                 file: value,
                 size: len,
@@ -192,6 +577,49 @@ mod tests {
 
         MultiFile::new(vec![a.into(), b.into()])
     }
+
+    /// A reader returning at most 4 bytes per read, as network sources do.
+    struct ShortReader(Cursor<Vec<u8>>);
+
+    impl Read for ShortReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(4);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    impl Seek for ShortReader {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    fn new_short_file() -> MultiFile<ShortReader> {
+        let file = |data: &[u8]| File {
+            file: ShortReader(Cursor::new(data.to_vec())),
+            size: data.len() as u64,
+            filename: "short".to_string(),
+        };
+
+        MultiFile::new(vec![file(b"abcdef"), file(b"ghi")])
+    }
+
+    #[test]
+    fn test_open() {
+        let path = std::env::temp_dir().join("exact_reader_test_open.bin");
+        std::fs::write(&path, [1u8, 2, 3, 4]).unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(file.size, 4);
+        assert_eq!(file.filename, path.to_string_lossy());
+
+        let mut buf = [0u8; 4];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3, 4]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_read1() {
         let a = Cursor::new(vec![1u8, 2, 3]);
@@ -211,24 +639,25 @@ mod tests {
 
         {
             let mut buf = [0u8; 3];
-            file.read(&mut buf).unwrap();
+            let _ = file.read(&mut buf).unwrap();
             assert_eq!(buf, [1, 2, 3])
         }
 
         {
             let mut buf = [0u8; 1];
-            file.read(&mut buf).unwrap();
+            let _ = file.read(&mut buf).unwrap();
             assert_eq!(buf, [4])
         }
 
         {
             let mut buf = [0u8; 5];
-            file.read(&mut buf).unwrap();
+            let _ = file.read(&mut buf).unwrap();
             assert_eq!(buf, [5, 6, 0, 0, 0])
         }
     }
 
     #[test]
+    #[allow(clippy::seek_from_current)]
     fn test_seek() {
         let mut file = new_file();
 
@@ -255,7 +684,7 @@ mod tests {
             let _ = file.seek(std::io::SeekFrom::Current(-1));
 
             let _ = file.read(&mut buf).unwrap();
-            assert_eq!(buf, [4, 5])
+            assert_eq!(buf, [5, 6])
         }
 
         {
@@ -268,6 +697,287 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_seek_many() {
+        // segments of 0 to 4 bytes, with zero-length ones in between
+        let mut data = Vec::new();
+        let files = (0..1000u32)
+            .map(|idx| {
+                let segment: Vec<u8> = (0..idx % 5).map(|_| data.len() as u8).collect();
+                data.extend_from_slice(&segment);
+                Cursor::new(segment).into()
+            })
+            .collect();
+        let mut file = MultiFile::new(files);
+        let size = data.len() as u64;
+
+        for target in [size, 7, size - 1, 0, size / 2, 1, size] {
+            assert_eq!(file.seek(std::io::SeekFrom::Start(target)).unwrap(), target);
+            let mut buf = [0u8; 1];
+            let read = file.read(&mut buf).unwrap();
+            assert_eq!(read, usize::from(target < size));
+            assert_eq!(buf[..read], data[target as usize..][..read]);
+        }
+
+        let err = file.seek(std::io::SeekFrom::Start(size + 1)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_short_reads() {
+        let mut out = Vec::new();
+        new_short_file().read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"abcdefghi");
+
+        // a short read does not end the file, and the position follows what was read
+        let mut file = new_short_file();
+        let mut buf = [0u8; 9];
+        assert_eq!(file.read(&mut buf).unwrap(), 9);
+        assert_eq!(buf, *b"abcdefghi");
+        assert_eq!(file.stream_position().unwrap(), 9);
+
+        let mut reader = crate::ExactReader::new_multi(new_short_file());
+        reader.seek(std::io::SeekFrom::Start(2)).unwrap();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"cdefghi");
+    }
+
+    #[test]
+    fn test_read_position() {
+        let mut file = new_file();
+
+        // consecutive reads within a file move the position by what they read
+        let mut buf = [0u8; 1];
+        for expected in 1..=6u8 {
+            file.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, [expected]);
+            assert_eq!(file.stream_position().unwrap(), u64::from(expected));
+        }
+
+        file.seek(std::io::SeekFrom::Start(4)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(file.stream_position().unwrap(), 5);
+        assert_eq!(file.seek(std::io::SeekFrom::Current(-2)).unwrap(), 3);
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [4]);
+    }
+
+    #[test]
+    fn test_seek_empty() {
+        let mut file = MultiFile::<Cursor<Vec<u8>>>::new(Vec::new());
+
+        // the empty stream can still be seeked to its start
+        assert_eq!(file.seek(std::io::SeekFrom::Start(0)).unwrap(), 0);
+        assert_eq!(file.seek(std::io::SeekFrom::End(0)).unwrap(), 0);
+        let err = file.seek(std::io::SeekFrom::Start(1)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_update_files() {
+        let mut file = new_file();
+        let mut buf = [0u8; 4];
+        file.read_exact(&mut buf).unwrap();
+
+        file.push_file(Cursor::new(vec![7u8, 8]).into());
+        assert_eq!(file.size(), 8);
+
+        // the cursor follows its data
+        file.insert_file(0, Cursor::new(vec![0u8]).into()).unwrap();
+        assert_eq!(file.stream_position().unwrap(), 5);
+        assert_eq!(file.size(), 9);
+
+        let replaced = file
+            .replace_file(2, Cursor::new(vec![4u8, 5]).into())
+            .unwrap();
+        assert_eq!(replaced.size, 3);
+        let mut buf = [0u8; 1];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [5]);
+
+        // removing the current file moves the cursor to the next one
+        file.remove_file(2).unwrap();
+        assert_eq!(file.stream_position().unwrap(), 4);
+        let mut out = Vec::new();
+        file.read_to_end(&mut out).unwrap();
+        assert_eq!(out, [7, 8]);
+
+        file.remove_file(2).unwrap();
+        assert_eq!(file.stream_position().unwrap(), 4);
+        assert_eq!(file.size(), 4);
+        let _ = file.seek(std::io::SeekFrom::Start(1)).unwrap();
+        file.read_to_end(&mut out).unwrap();
+        assert_eq!(out, [7, 8, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_remove_last() {
+        let mut file = MultiFile::new(vec![Cursor::new(vec![1u8, 2, 3]).into()]);
+        file.remove_file(0).unwrap();
+        assert_eq!(file.size(), 0);
+
+        let mut buf = [0u8; 4];
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+        assert_eq!(
+            file.read_vectored(&mut [IoSliceMut::new(&mut buf)])
+                .unwrap(),
+            0
+        );
+        assert_eq!(file.stream_position().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_remove_current_at_end() {
+        let mut file = MultiFile::new(vec![Cursor::new(vec![73u8, 74, 75]).into()]);
+        file.insert_file(0, Cursor::new(vec![76u8]).into()).unwrap();
+        file.remove_file(1).unwrap();
+        assert_eq!(file.stream_position().unwrap(), 1);
+
+        // the previous file is read through, so the cursor stays at the end
+        file.insert_file(0, Cursor::new(vec![77u8, 78, 79]).into())
+            .unwrap();
+        assert_eq!(file.stream_position().unwrap(), 4);
+        let mut out = Vec::new();
+        file.read_to_end(&mut out).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_replace_before_cursor() {
+        let mut file = MultiFile::new(vec![
+            Cursor::new(vec![65u8]).into(),
+            Cursor::new(vec![66u8, 67, 68]).into(),
+            Cursor::new(vec![69u8, 70]).into(),
+        ]);
+        let _ = file.seek(std::io::SeekFrom::Start(5)).unwrap();
+
+        file.replace_file(0, Cursor::new(vec![71u8]).into())
+            .unwrap();
+        file.remove_file(2).unwrap();
+        file.remove_file(1).unwrap();
+        assert_eq!(file.stream_position().unwrap(), 1);
+        assert_eq!(file.read(&mut [0u8; 1]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_segments() {
+        let mut file = new_file();
+        file.insert_file(1, Cursor::new(vec![]).into()).unwrap();
+
+        assert_eq!(file.locate(0), Some((0, "cursor", 0)));
+        assert_eq!(file.locate(3), Some((2, "cursor", 0)));
+        assert_eq!(file.locate(6), Some((2, "cursor", 3)));
+        assert_eq!(file.locate(7), None);
+
+        assert_eq!(file.segment_range(1), Some(3..3));
+        assert_eq!(file.segment_range(2), Some(3..6));
+        assert_eq!(file.segment_range(3), None);
+
+        let ranges: Vec<_> = file.segments().map(|s| (s.idx, s.range)).collect();
+        assert_eq!(ranges, [(0, 0..3), (1, 3..3), (2, 3..6)]);
+
+        let _ = file.seek(std::io::SeekFrom::Start(4)).unwrap();
+        let current = file.current_segment().unwrap();
+        assert_eq!((current.idx, current.range), (2, 3..6));
+        assert_eq!(current.file.size, 3);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut file = new_file();
+
+        let mut buf = [0u8; 4];
+        file.read_exact(&mut buf).unwrap();
+        let _ = file.seek(std::io::SeekFrom::Start(1)).unwrap();
+
+        let reads: Vec<_> = file.stats().iter().map(|s| s.reads).collect();
+        let seeks: Vec<_> = file.stats().iter().map(|s| s.seeks).collect();
+        assert_eq!(reads, [1, 1]);
+        assert_eq!(seeks, [1, 1]);
+
+        file.reset_stats();
+        assert_eq!(file.stats(), [FileStats::default(); 2]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_read_at() {
+        let paths = ["a", "b"]
+            .map(|name| std::env::temp_dir().join(format!("exact_reader_test_read_at_{name}.bin")));
+        std::fs::write(&paths[0], [1u8, 2, 3]).unwrap();
+        std::fs::write(&paths[1], [4u8, 5, 6]).unwrap();
+
+        let mut file = MultiFile::new(paths.iter().map(|p| File::open(p).unwrap()).collect());
+        let mut buf = [0u8; 4];
+        file.read_exact(&mut buf).unwrap();
+
+        let mut buf = [0u8; 5];
+        assert_eq!(file.read_at(2, &mut buf).unwrap(), 4);
+        assert_eq!(buf[..4], [3, 4, 5, 6]);
+        assert_eq!(file.read_at(6, &mut buf).unwrap(), 0);
+
+        // the positions of the files are left untouched
+        let mut buf = [0u8; 2];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [5, 6]);
+
+        let mut buf = [0u8; 4];
+        new_file().read_exact_at(1, &mut buf).unwrap();
+        assert_eq!(buf, [2, 3, 4, 5]);
+
+        for path in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_read_vectored() {
+        let mut file = new_file();
+
+        let (mut a, mut b) = ([0u8; 2], [0u8; 3]);
+        let read = file
+            .read_vectored(&mut [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)])
+            .unwrap();
+        assert_eq!(read, 5);
+        assert_eq!((a, b), ([1, 2], [3, 4, 5]));
+
+        // a single read per file
+        let reads: Vec<_> = file.stats().iter().map(|s| s.reads).collect();
+        assert_eq!(reads, [1, 1]);
+
+        let mut c = [0u8; 4];
+        assert_eq!(
+            file.read_vectored(&mut [IoSliceMut::new(&mut c)]).unwrap(),
+            1
+        );
+        assert_eq!(c[0], 6);
+
+        let (mut a, mut b) = ([0u8; 1], [0u8; 4]);
+        let read = file
+            .read_vectored_at(1, &mut [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)])
+            .unwrap();
+        assert_eq!(read, 5);
+        assert_eq!((a, b), ([2], [3, 4, 5, 6]));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_preadv() {
+        let path = std::env::temp_dir().join("exact_reader_test_preadv.bin");
+        std::fs::write(&path, [1u8, 2, 3, 4, 5]).unwrap();
+
+        let file = File::open(&path).unwrap();
+        let (mut a, mut b) = ([0u8; 2], [0u8; 4]);
+        let read = file
+            .read_vectored_at(1, &mut [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)])
+            .unwrap();
+        assert_eq!(read, 4);
+        assert_eq!((a, b[..2].to_vec()), ([2, 3], vec![4, 5]));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_seek2() {
         let mut file = new_file();
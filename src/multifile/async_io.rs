@@ -0,0 +1,138 @@
+use std::{
+    io::SeekFrom,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+use super::{File, MultiFile, SeekPlan};
+
+/// A `MultiFile` seek in progress, moving one file at a time.
+pub(super) struct PendingSeek {
+    /// The planned file seeks.
+    plan: SeekPlan,
+    /// The index of the next skipped file to move.
+    next: usize,
+    /// Whether the seek of the current file was started.
+    started: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for File<R> {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().file).poll_read(cx, buf)
+    }
+}
+
+impl<R: AsyncSeek + Unpin> AsyncSeek for File<R> {
+    #[inline]
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        Pin::new(&mut self.get_mut().file).start_seek(position)
+    }
+
+    #[inline]
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        Pin::new(&mut self.get_mut().file).poll_complete(cx)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for MultiFile<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            let Some(file) = this.files.get_mut(this.current_file_idx) else {
+                return Poll::Ready(Ok(()));
+            };
+
+            let before = buf.filled().len();
+            let result = ready!(Pin::new(file).poll_read(cx, buf));
+            this.stats[this.current_file_idx].reads += 1;
+            result?;
+            let read = buf.filled().len() - before;
+
+            // an empty read moves on to the next file, unless this is the last one
+            if read > 0 || buf.remaining() == 0 || this.current_file_idx + 1 >= this.files.len() {
+                this.infile_offset += read as u64;
+                return Poll::Ready(Ok(()));
+            }
+
+            this.cumul_offset += this.files[this.current_file_idx].size;
+            this.current_file_idx += 1;
+            this.infile_offset = 0;
+        }
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncSeek for MultiFile<R> {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        let this = self.get_mut();
+        let plan = this.plan_seek(position)?;
+        if this.files.is_empty() {
+            this.apply_seek(&plan, 0);
+            return Ok(());
+        }
+
+        this.pending_seek = Some(PendingSeek {
+            next: plan.skipped.start,
+            plan,
+            started: false,
+        });
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        let this = self.get_mut();
+
+        loop {
+            let Some(pending) = this.pending_seek.as_mut() else {
+                return Poll::Ready(Ok(this.physical_offset()));
+            };
+
+            // the files passed over are moved first, then the target file
+            let is_target = pending.next >= pending.plan.skipped.end;
+            let (idx, position) = if is_target {
+                (
+                    pending.plan.idx,
+                    SeekFrom::Start(pending.plan.infile_offset),
+                )
+            } else {
+                (pending.next, pending.plan.skip_to)
+            };
+            if !pending.started {
+                if let Err(err) = Pin::new(&mut this.files[idx]).start_seek(position) {
+                    this.pending_seek = None;
+                    return Poll::Ready(Err(err));
+                }
+                pending.started = true;
+                this.stats[idx].seeks += 1;
+            }
+
+            let res = match ready!(Pin::new(&mut this.files[idx]).poll_complete(cx)) {
+                Ok(res) => res,
+                Err(err) => {
+                    this.pending_seek = None;
+                    return Poll::Ready(Err(err));
+                }
+            };
+
+            if is_target {
+                let pending = this.pending_seek.take().unwrap();
+                return Poll::Ready(Ok(this.apply_seek(&pending.plan, res)));
+            }
+
+            pending.next += 1;
+            pending.started = false;
+        }
+    }
+}
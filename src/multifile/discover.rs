@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+use crate::utils::{natural_cmp, wildcard_match};
+
+use super::{File, MultiFile};
+
+impl MultiFile<std::fs::File> {
+    /// Creates a new `MultiFile` instance by opening the files at the given paths, in order.
+    ///
+    /// The sizes are taken from the metadata of the files, see [`File::open`].
+    pub fn from_paths<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> std::io::Result<Self> {
+        let files = paths
+            .into_iter()
+            .map(File::open)
+            .collect::<std::io::Result<_>>()?;
+
+        Ok(Self::new(files))
+    }
+
+    /// Creates a new `MultiFile` instance from the regular files of the directory,
+    /// in natural order of their names.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        Self::from_paths(list_dir(dir.as_ref(), |_| true)?)
+    }
+
+    /// Creates a new `MultiFile` instance from the regular files matching the pattern,
+    /// in natural order of their names, so `part-10` comes after `part-9`.
+    ///
+    /// The wildcards `*` and `?` are only supported in the file name, such as `data/part-*.bin`.
+    /// Fails with `InvalidInput` if the directory part of the pattern holds any.
+    /// A pattern matching no file gives an empty `MultiFile`.
+    pub fn from_glob(pattern: &str) -> std::io::Result<Self> {
+        let pattern = Path::new(pattern);
+        let dir = pattern.parent().unwrap_or(Path::new(""));
+        let Some(name_pattern) = pattern.file_name().map(|name| name.to_string_lossy()) else {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        };
+        if dir.to_string_lossy().contains(['*', '?']) {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        }
+
+        Self::from_paths(list_dir(dir, |name| wildcard_match(&name_pattern, name))?)
+    }
+
+    /// Creates a new `MultiFile` instance from the members of a split file set,
+    /// given the path of any member, such as `archive.zip.001`.
+    ///
+    /// The members are the files sharing the name up to the numeric suffix,
+    /// with as many digits, in numeric order.
+    /// Fails with `InvalidInput` if the name does not end with digits.
+    pub fn from_split_set<P: AsRef<Path>>(member: P) -> std::io::Result<Self> {
+        let member = member.as_ref();
+        let dir = member.parent().unwrap_or(Path::new(""));
+        let name = member
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        let digits = name.len() - prefix.len();
+        if digits == 0 {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        }
+
+        Self::from_paths(list_dir(dir, |name| {
+            name.strip_prefix(prefix).is_some_and(|suffix| {
+                suffix.len() == digits && suffix.bytes().all(|b| b.is_ascii_digit())
+            })
+        })?)
+    }
+}
+
+/// Lists the regular files of the directory whose name passes the filter, in natural order.
+///
+/// The paths are joined to `dir` as given, the current directory is listed when it is empty.
+fn list_dir(dir: &Path, filter: impl Fn(&str) -> bool) -> std::io::Result<Vec<PathBuf>> {
+    let listed = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(listed)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // the symbolic links are followed to check for regular files,
+        // the broken ones and the files removed meanwhile are skipped
+        if filter(&name) && std::fs::metadata(entry.path()).is_ok_and(|meta| meta.is_file()) {
+            entries.push((name, entry.file_name()));
+        }
+    }
+    entries.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+
+    // the paths are joined from the original names, which may not be valid UTF-8
+    Ok(entries
+        .into_iter()
+        .map(|(_, name)| dir.join(name))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek};
+
+    /// Creates a fresh directory with the given files.
+    fn temp_dir(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("exact_reader_test_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        for (name, data) in files {
+            std::fs::write(dir.join(name), data).unwrap();
+        }
+        dir
+    }
+
+    fn read_all(mut file: MultiFile<std::fs::File>) -> Vec<u8> {
+        let mut out = Vec::new();
+        file.read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_glob() {
+        let dir = temp_dir(
+            "glob",
+            &[
+                ("part-10.bin", b"c"),
+                ("part-9.bin", b"b"),
+                ("part-1.bin", b"a"),
+                ("other.bin", b"x"),
+            ],
+        );
+
+        let pattern = dir.join("part-*.bin");
+        let file = MultiFile::from_glob(&pattern.to_string_lossy()).unwrap();
+        assert_eq!(
+            file.files[2].filename,
+            dir.join("part-10.bin").to_string_lossy()
+        );
+        assert_eq!(read_all(file), b"abc");
+
+        assert_eq!(read_all(MultiFile::from_dir(&dir).unwrap()), b"xabc");
+
+        // a pattern matching nothing gives an empty stream
+        let mut file = MultiFile::from_glob(&dir.join("zz*").to_string_lossy()).unwrap();
+        assert_eq!(file.size(), 0);
+        assert_eq!(file.seek(std::io::SeekFrom::Start(0)).unwrap(), 0);
+        assert_eq!(read_all(file), b"");
+
+        let err = MultiFile::from_glob(&dir.join("*").join("x").to_string_lossy())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_odd_entries() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = temp_dir("odd_entries", &[("part-1.bin", b"a")]);
+        std::os::unix::fs::symlink(dir.join("missing"), dir.join("part-2.bin")).unwrap();
+        let name = std::ffi::OsStr::from_bytes(b"part-3\xff.bin");
+        std::fs::write(dir.join(name), b"b").unwrap();
+
+        // the broken link is skipped, the name that is not UTF-8 is opened as is
+        let file = MultiFile::from_glob(&dir.join("part-*").to_string_lossy()).unwrap();
+        assert_eq!(read_all(file), b"ab");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_set() {
+        let dir = temp_dir(
+            "split_set",
+            &[
+                ("archive.zip.002", b"456"),
+                ("archive.zip.001", b"123"),
+                ("archive.zip.010", b"7"),
+                ("archive.zip.1", b"x"),
+                ("archive.zip", b"x"),
+            ],
+        );
+
+        let file = MultiFile::from_split_set(dir.join("archive.zip.002")).unwrap();
+        assert_eq!(file.size(), 7);
+        assert_eq!(read_all(file), b"1234567");
+
+        let err = MultiFile::from_split_set(dir.join("archive.zip"))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,306 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{read_at::ReadAt, utils::calculate_seek};
+
+use super::{File, MultiFile};
+
+/// An open handle of the pool.
+struct Handle {
+    /// The id of the `LazyFile` owning the handle.
+    id: usize,
+    /// The opened file.
+    file: std::fs::File,
+    /// The position of the opened file.
+    position: u64,
+}
+
+/// The open handles shared by the files of a lazy `MultiFile`.
+struct HandlePool {
+    /// The maximum number of open handles.
+    max_open: usize,
+    /// The open handles, from the least to the most recently used.
+    handles: Mutex<Vec<Handle>>,
+}
+
+/// The `LazyFile` struct is a file opened only when it is read,
+/// see [`MultiFile::open_lazy`].
+///
+/// Seeks only move the position, and the handle is closed once the end of the file is read,
+/// or when it is the least recently used one of a full pool.
+pub struct LazyFile {
+    /// The path of the file.
+    path: PathBuf,
+    /// The id of the file within the pool.
+    id: usize,
+    /// The size of the file in bytes.
+    size: u64,
+    /// The position within the file.
+    position: u64,
+    /// The pool of open handles.
+    pool: Arc<HandlePool>,
+}
+
+impl LazyFile {
+    /// The path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Runs `f` on the open handle of the file, opening it if needed.
+    ///
+    /// The handle is taken out of the pool while it is used, so the pool is not locked
+    /// during the I/O. It is put back afterwards, closing the least recently used handle
+    /// when the pool is full.
+    fn with_handle<T>(
+        &self,
+        f: impl FnOnce(&mut Handle) -> std::io::Result<T>,
+    ) -> std::io::Result<T> {
+        let handle = {
+            let mut handles = self.lock_handles();
+            handles
+                .iter()
+                .position(|handle| handle.id == self.id)
+                .map(|pos| handles.remove(pos))
+        };
+        let mut handle = match handle {
+            Some(handle) => handle,
+            None => Handle {
+                id: self.id,
+                file: std::fs::File::open(&self.path)?,
+                position: 0,
+            },
+        };
+
+        let result = f(&mut handle);
+
+        let mut handles = self.lock_handles();
+        // a concurrent positional read may have put back a handle of the same file
+        if handles.iter().all(|open| open.id != self.id) {
+            if handles.len() >= self.pool.max_open {
+                handles.remove(0);
+            }
+            handles.push(handle);
+        }
+
+        result
+    }
+
+    /// Locks the open handles of the pool.
+    fn lock_handles(&self) -> std::sync::MutexGuard<'_, Vec<Handle>> {
+        self.pool
+            .handles
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Closes the handle of the file, if it is open.
+    fn close(&self) {
+        self.lock_handles().retain(|handle| handle.id != self.id);
+    }
+}
+
+impl Read for LazyFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || self.position >= self.size {
+            return Ok(0);
+        }
+
+        // the data past the declared size is not part of the stream
+        let len = (self.size - self.position).min(buf.len() as u64) as usize;
+        let buf = &mut buf[..len];
+
+        let position = self.position;
+        let read = self.with_handle(|handle| {
+            if handle.position != position {
+                // the position of the handle is unknown if the seek fails
+                handle.position = u64::MAX;
+                handle.position = handle.file.seek(SeekFrom::Start(position))?;
+            }
+
+            let read = handle.file.read(buf);
+            handle.position = match read {
+                Ok(read) => handle.position + read as u64,
+                // the position of the handle is unknown after a failed read
+                Err(_) => u64::MAX,
+            };
+            read
+        })?;
+        self.position += read as u64;
+
+        // the file is passed over once its end is read
+        if read == 0 || self.position >= self.size {
+            self.close();
+        }
+
+        Ok(read)
+    }
+}
+
+impl Seek for LazyFile {
+    /// Moves the position, the file is only opened by the next read.
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = calculate_seek(self.size, self.position, pos)?;
+        Ok(self.position)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.position)
+    }
+}
+
+/// Uses `pread`, the position of the file is left untouched.
+#[cfg(unix)]
+impl ReadAt for LazyFile {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || offset >= self.size {
+            return Ok(0);
+        }
+
+        let len = (self.size - offset).min(buf.len() as u64) as usize;
+        self.with_handle(|handle| handle.file.read_at(offset, &mut buf[..len]))
+    }
+}
+
+impl MultiFile<LazyFile> {
+    /// Creates a new `MultiFile` instance from the paths and the sizes of the files,
+    /// opening them only when they are read.
+    ///
+    /// At most `max_open` files are open at once, the least recently used one is closed
+    /// to open another, and the files are closed once their end is read.
+    pub fn open_lazy<P: Into<PathBuf>>(
+        entries: impl IntoIterator<Item = (P, u64)>,
+        max_open: usize,
+    ) -> Self {
+        let pool = Arc::new(HandlePool {
+            max_open: max_open.max(1),
+            handles: Mutex::new(Vec::new()),
+        });
+
+        let files = entries
+            .into_iter()
+            .enumerate()
+            .map(|(id, (path, size))| {
+                let path = path.into();
+                File {
+                    filename: path.to_string_lossy().into_owned(),
+                    file: LazyFile {
+                        path,
+                        id,
+                        size,
+                        position: 0,
+                        pool: pool.clone(),
+                    },
+                    size,
+                }
+            })
+            .collect();
+
+        Self::new(files)
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use super::*;
+
+    fn open_handles(file: &MultiFile<LazyFile>) -> usize {
+        let pool = &file.files[0].file.pool;
+        pool.handles.lock().unwrap().len()
+    }
+
+    #[test]
+    fn test_lazy() {
+        let mut data = Vec::new();
+        let entries: Vec<_> = (0..10u8)
+            .map(|idx| {
+                let path = std::env::temp_dir().join(format!("exact_reader_test_lazy_{idx}.bin"));
+                let segment = vec![idx; idx as usize % 4 + 1];
+                std::fs::write(&path, &segment).unwrap();
+                data.extend_from_slice(&segment);
+                (path, segment.len() as u64)
+            })
+            .collect();
+
+        let mut file = MultiFile::open_lazy(entries.clone(), 2);
+        assert_eq!(file.size(), data.len() as u64);
+        assert_eq!(open_handles(&file), 0);
+
+        // the files passed over are closed
+        let mut buf = [0u8; 4];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[..4]);
+        assert_eq!(open_handles(&file), 1);
+
+        let mut out = Vec::new();
+        file.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data[4..]);
+        assert_eq!(open_handles(&file), 0);
+
+        // seeks do not open files, the pool is bounded for random reads
+        file.seek(SeekFrom::Start(1)).unwrap();
+        assert_eq!(open_handles(&file), 0);
+        for offset in [1, 9, 20, 4, 12] {
+            let mut buf = [0u8; 1];
+            file.read_exact_at(offset, &mut buf).unwrap();
+            assert_eq!(buf[0], data[offset as usize]);
+            assert!(open_handles(&file) <= 2);
+        }
+
+        let mut buf = [0u8; 4];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[1..5]);
+
+        for (path, _) in entries {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_failed_open() {
+        let paths = ["a", "missing"].map(|name| {
+            std::env::temp_dir().join(format!("exact_reader_test_lazy_open_{name}.bin"))
+        });
+        std::fs::write(&paths[0], [1u8, 2]).unwrap();
+
+        let file = MultiFile::open_lazy(paths.iter().map(|path| (path, 2)), 1);
+        let mut buf = [0u8; 1];
+        file.read_exact_at(0, &mut buf).unwrap();
+        assert_eq!(open_handles(&file), 1);
+
+        // the open handle is kept when another file fails to open
+        let err = file.read_exact_at(2, &mut buf).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(open_handles(&file), 1);
+
+        std::fs::remove_file(&paths[0]).unwrap();
+    }
+
+    #[test]
+    fn test_declared_size() {
+        let paths = ["a", "b"].map(|name| {
+            std::env::temp_dir().join(format!("exact_reader_test_lazy_size_{name}.bin"))
+        });
+        std::fs::write(&paths[0], [1u8; 10]).unwrap();
+        std::fs::write(&paths[1], [2u8; 4]).unwrap();
+
+        // the first file grew past its declared size
+        let mut file = MultiFile::open_lazy(paths.iter().map(|path| (path, 4)), 2);
+        let mut out = Vec::new();
+        file.read_to_end(&mut out).unwrap();
+        assert_eq!(out, [1, 1, 1, 1, 2, 2, 2, 2]);
+        assert_eq!(file.stream_position().unwrap(), 8);
+
+        let mut buf = [0u8; 6];
+        assert_eq!(file.read_at(2, &mut buf).unwrap(), 6);
+        assert_eq!(buf, [1, 1, 2, 2, 2, 2]);
+
+        for path in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread::JoinHandle,
+};
+
+use crate::utils::calculate_seek;
+
+/// A read requested from the worker thread.
+struct Request {
+    /// The physical offset to read from.
+    offset: u64,
+    /// The number of bytes to read.
+    len: usize,
+    /// A recycled buffer to read into.
+    data: Vec<u8>,
+}
+
+/// A range read by the worker thread.
+struct Chunk {
+    /// The physical offset of the first byte.
+    offset: u64,
+    /// The data read, or the error of the inner reader.
+    data: std::io::Result<Vec<u8>>,
+    /// Whether the end of the inner reader was reached.
+    eof: bool,
+}
+
+/// The `Prefetcher` struct moves a reader to a worker thread that reads ahead of the consumer.
+///
+/// Whenever a read is served, the range right after the last fetched one is read in the
+/// background, so that sequential reads rarely wait for the inner reader.
+/// Two buffers are handed back and forth between the threads, one being read and one being filled.
+pub struct Prefetcher<R> {
+    /// The requests sent to the worker thread, dropped to stop it.
+    requests: Option<Sender<Request>>,
+    /// The chunks read by the worker thread.
+    chunks: Receiver<Chunk>,
+    /// The worker thread owning the inner reader.
+    worker: Option<JoinHandle<R>>,
+
+    /// The chunk reads are served from.
+    current: Chunk,
+    /// The buffer handed to the next request.
+    spare: Vec<u8>,
+    /// The offset of the request sent to the worker thread, if any.
+    in_flight: Option<u64>,
+
+    /// The current position of the reader.
+    position: u64,
+    /// The size of the inner reader.
+    size: u64,
+    /// The number of bytes read ahead.
+    read_ahead: usize,
+}
+
+impl<R: Read + Seek + Send + 'static> Prefetcher<R> {
+    /// Creates a new `Prefetcher` reading `read_ahead` bytes ahead of the consumer.
+    ///
+    /// The size is discovered by seeking to the end of the reader,
+    /// reading starts from the current position of the reader.
+    pub fn new(mut file: R, read_ahead: usize) -> std::io::Result<Self> {
+        let offset = file.stream_position()?;
+        let size = file.seek(SeekFrom::End(0))?;
+
+        Ok(Self::spawn(file, size, offset, size, read_ahead))
+    }
+
+    /// Moves the reader to a new worker thread.
+    ///
+    /// `inner_offset` is the position of the reader, `u64::MAX` if unknown.
+    pub(crate) fn spawn(
+        file: R,
+        inner_offset: u64,
+        position: u64,
+        size: u64,
+        read_ahead: usize,
+    ) -> Self {
+        let (requests, worker_requests) = channel();
+        let (worker_chunks, chunks) = channel();
+        let worker =
+            std::thread::spawn(move || work(file, inner_offset, worker_requests, worker_chunks));
+
+        Self {
+            requests: Some(requests),
+            chunks,
+            worker: Some(worker),
+            current: Chunk {
+                offset: 0,
+                data: Ok(Vec::new()),
+                eof: false,
+            },
+            spare: Vec::new(),
+            in_flight: None,
+            position,
+            size,
+            read_ahead,
+        }
+    }
+
+    /// Stops the worker thread and returns the inner reader.
+    pub fn into_inner(mut self) -> R {
+        self.stop().expect("the worker thread panicked")
+    }
+}
+
+impl<R> Prefetcher<R> {
+    /// The number of bytes read ahead.
+    pub fn read_ahead(&self) -> usize {
+        self.read_ahead
+    }
+
+    /// Sends a request for the range at `offset`, unless one is already in flight.
+    fn request(&mut self, offset: u64, len: usize) {
+        if self.in_flight.is_some() {
+            return;
+        }
+
+        let request = Request {
+            offset,
+            len,
+            data: std::mem::take(&mut self.spare),
+        };
+        if let Some(requests) = self.requests.as_ref() {
+            if requests.send(request).is_ok() {
+                self.in_flight = Some(offset);
+            }
+        }
+    }
+
+    /// Waits for the request in flight, making its chunk the current one.
+    fn receive(&mut self) -> std::io::Result<()> {
+        self.in_flight = None;
+        let chunk = self
+            .chunks
+            .recv()
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+
+        let previous = std::mem::replace(&mut self.current, chunk);
+        if let Ok(mut data) = previous.data {
+            data.clear();
+            self.spare = data;
+        }
+
+        match &self.current.data {
+            Ok(_) => Ok(()),
+            Err(err) => Err(std::io::Error::new(err.kind(), err.to_string())),
+        }
+    }
+
+    /// Copies the data of the current chunk at the current position, returning `None` if not cached.
+    fn copy_current(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let Chunk { offset, data, eof } = &self.current;
+        let data = data.as_ref().ok()?;
+        let end = offset + data.len() as u64;
+
+        if *eof && self.position >= end {
+            return Some(0);
+        }
+        if !(*offset..end).contains(&self.position) {
+            return None;
+        }
+
+        let start = (self.position - offset) as usize;
+        let size = buf.len().min(data.len() - start);
+        buf[..size].copy_from_slice(&data[start..start + size]);
+
+        Some(size)
+    }
+
+    /// Stops the worker thread, returning the inner reader unless it panicked.
+    fn stop(&mut self) -> Option<R> {
+        // the worker thread stops once the requests are closed
+        self.requests = None;
+        self.worker.take()?.join().ok()
+    }
+}
+
+impl<R> Read for Prefetcher<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if let Some(size) = self.copy_current(buf) {
+                self.position += size as u64;
+
+                // the next range is read while this one is consumed
+                if !self.current.eof && self.read_ahead > 0 {
+                    let len = self.current.data.as_ref().map_or(0, Vec::len);
+                    let end = self.current.offset + len as u64;
+                    self.request(end, self.read_ahead);
+                }
+
+                return Ok(size);
+            }
+
+            if self.in_flight.is_none() {
+                self.request(self.position, buf.len().max(self.read_ahead));
+            }
+            self.receive()?;
+        }
+    }
+}
+
+impl<R> Seek for Prefetcher<R> {
+    /// Moves the position, the worker thread only seeks on the next request.
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = calculate_seek(self.size, self.position, pos)?;
+        Ok(self.position)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.position)
+    }
+}
+
+impl<R> Drop for Prefetcher<R> {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// Serves the requests on the worker thread until they are closed.
+fn work<R: Read + Seek>(
+    mut file: R,
+    mut inner_offset: u64,
+    requests: Receiver<Request>,
+    chunks: Sender<Chunk>,
+) -> R {
+    while let Ok(Request { offset, len, data }) = requests.recv() {
+        let chunk = read_chunk(&mut file, &mut inner_offset, offset, len, data);
+        if chunks.send(chunk).is_err() {
+            break;
+        }
+    }
+
+    file
+}
+
+/// Reads `len` bytes at `offset`, fewer only at the end of the reader.
+fn read_chunk<R: Read + Seek>(
+    file: &mut R,
+    inner_offset: &mut u64,
+    offset: u64,
+    len: usize,
+    mut data: Vec<u8>,
+) -> Chunk {
+    let mut read_range = || {
+        if *inner_offset != offset {
+            // the position of the inner reader is unknown if the seek fails
+            *inner_offset = u64::MAX;
+            *inner_offset = file.seek(SeekFrom::Start(offset))?;
+        }
+
+        data.resize(len, 0);
+        let mut filled = 0;
+        while filled < len {
+            match file.read(&mut data[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    // the position of the inner reader is unknown after a failed read
+                    *inner_offset = u64::MAX;
+                    return Err(err);
+                }
+            }
+        }
+        *inner_offset += filled as u64;
+        data.truncate(filled);
+
+        Ok(filled < len)
+    };
+
+    match read_range() {
+        Ok(eof) => Chunk {
+            offset,
+            data: Ok(data),
+            eof,
+        },
+        Err(err) => Chunk {
+            offset,
+            data: Err(err),
+            eof: false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExactReader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_sequential() {
+        let data: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+        let mut reader = ExactReader::new(Cursor::new(data.clone()))
+            .unwrap()
+            .with_prefetch(256);
+
+        let mut buf = [0u8; 100];
+        for chunk in data.chunks(100) {
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, chunk);
+        }
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_seek() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut prefetcher = Prefetcher::new(Cursor::new(data.clone()), 16).unwrap();
+
+        let mut buf = [0u8; 8];
+        for offset in [200, 10, 250, 0, 24] {
+            prefetcher.seek(SeekFrom::Start(offset)).unwrap();
+            let read = prefetcher.read(&mut buf).unwrap();
+            let offset = offset as usize;
+            assert_eq!(buf[..read], data[offset..offset + read]);
+        }
+
+        prefetcher.seek(SeekFrom::End(-4)).unwrap();
+        let mut out = Vec::new();
+        prefetcher.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data[252..]);
+
+        let cursor = prefetcher.into_inner();
+        assert_eq!(cursor.get_ref().len(), 256);
+    }
+}
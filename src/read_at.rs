@@ -0,0 +1,100 @@
+use std::io::{Cursor, IoSliceMut};
+
+/// The `ReadAt` trait reads at a given offset, leaving the position of the reader untouched.
+pub trait ReadAt {
+    /// Reads into `buf` from the given offset, returning the number of bytes read.
+    ///
+    /// As with `Read::read`, fewer bytes than requested may be read, and `0` means the end.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    /// Reads into the slices, in order, from the given offset.
+    ///
+    /// The default implementation only reads into the first non-empty slice.
+    fn read_vectored_at(&self, offset: u64, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<usize> {
+        match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+            Some(buf) => self.read_at(offset, buf),
+            None => Ok(0),
+        }
+    }
+
+    /// Reads exactly `buf.len()` bytes from the given offset.
+    ///
+    /// Fails with `UnexpectedEof` when the end is reached first.
+    fn read_exact_at(&self, mut offset: u64, mut buf: &mut [u8]) -> std::io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(offset, buf) {
+                Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => {
+                    buf = &mut buf[read..];
+                    offset += read as u64;
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Uses `pread`, and `preadv` on Linux, the file cursor is left untouched.
+#[cfg(unix)]
+impl ReadAt for std::fs::File {
+    #[inline]
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, offset)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn read_vectored_at(&self, offset: u64, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<usize> {
+        use std::os::fd::AsRawFd;
+
+        /// The maximum number of slices `preadv` accepts on Linux.
+        const MAX_SLICES: usize = 1024;
+
+        let offset = libc::off_t::try_from(offset)
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        // the extra slices are left to the next call, as with `readv`
+        let count = bufs.len().min(MAX_SLICES) as libc::c_int;
+
+        // SAFETY: `IoSliceMut` is ABI compatible with `iovec` on unix,
+        // and the slices are valid for writes for their whole length
+        let read = unsafe {
+            libc::preadv(
+                self.as_raw_fd(),
+                bufs.as_mut_ptr() as *const libc::iovec,
+                count,
+                offset,
+            )
+        };
+        if read < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(read as usize)
+    }
+}
+
+impl<T: AsRef<[u8]>> ReadAt for Cursor<T> {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let data = self.get_ref().as_ref();
+        let start = offset.min(data.len() as u64) as usize;
+        let size = buf.len().min(data.len() - start);
+        buf[..size].copy_from_slice(&data[start..start + size]);
+
+        Ok(size)
+    }
+
+    fn read_vectored_at(&self, offset: u64, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<usize> {
+        let mut taken = 0;
+        for buf in bufs {
+            let read = self.read_at(offset + taken as u64, buf)?;
+            taken += read;
+            if read < buf.len() {
+                break;
+            }
+        }
+
+        Ok(taken)
+    }
+}
@@ -1,12 +1,12 @@
-use std::{
-    io::{Read, Seek},
-    ops::RangeInclusive,
-};
+use std::io::{BufRead, IoSliceMut, Read, Seek, SeekFrom};
 
 use crate::{
+    buffer::{BufferState, Fetch, DEFAULT_RESERVE_SIZE},
     multifile::{File, MultiFile},
-    utils::calculate_seek,
-    vec_deq::VecDeque,
+    prefetch::Prefetcher,
+    read_at::ReadAt,
+    stats::Stats,
+    utils::{find_pattern, rfind_pattern},
 };
 
 /// The `ExactReader` struct simplifies reading data from a file(s).
@@ -14,34 +14,33 @@ pub struct ExactReader<R> {
     /// The inner reader for the file.
     file: R,
 
-    /// The active range of file offsets within the buffer.
-    file_offset_view: RangeInclusive<usize>,
-
-    /// The size of the file(s).
-    size: usize,
-
-    /// The buffer used for caching data read from the file(s).
-    buffer: VecDeque<u8>,
-    /// The offset within the buffer
-    buffer_offset: usize,
-
-    /// Seek position to be used on `reserve`
-    seeked: Option<usize>,
+    /// The buffer and the reservation state.
+    state: BufferState,
 }
 
 impl<R: Read + Seek> ExactReader<MultiFile<R>> {
     /// Creates a new `ExactReader` instance for reading data from multiple files.
     pub fn new_multi(file: MultiFile<R>) -> Self {
         let size = file.size();
+        Self::with_size(file, size, 0)
+    }
 
-        Self {
-            file,
-            size,
-            buffer: VecDeque::new(),
-            file_offset_view: 0..=0,
-            buffer_offset: 0,
-            seeked: None,
-        }
+    /// The inner `MultiFile`, to map the offsets of the reader to files with [`MultiFile::locate`].
+    ///
+    /// The cursor of the `MultiFile` is usually ahead of the reader, as it fills the buffer.
+    pub fn files(&self) -> &MultiFile<R> {
+        &self.file
+    }
+
+    /// Runs `f` on the inner `MultiFile` to add, remove or replace files, then refreshes the size.
+    ///
+    /// The cached data is dropped, as the files may have moved. The offset is kept
+    /// within the combined stream, so reading resumes there once new files are appended.
+    pub fn update_files<T>(&mut self, f: impl FnOnce(&mut MultiFile<R>) -> T) -> T {
+        let result = f(&mut self.file);
+        self.state.reset(self.file.size());
+
+        result
     }
 }
 
@@ -49,122 +48,1026 @@ impl<R: Read + Seek> ExactReader<File<R>> {
     /// Creates a new `ExactReader` instance for reading data from a single file.
     pub fn new_single(file: File<R>) -> Self {
         let size = file.size;
+        Self::with_size(file, size, 0)
+    }
+}
 
+impl<R: Read + Seek> ExactReader<R> {
+    /// Creates a new `ExactReader` instance for any reader.
+    ///
+    /// The size is discovered by seeking to the end of the reader,
+    /// reading starts from the current position of the reader.
+    pub fn new(mut file: R) -> std::io::Result<Self> {
+        let offset = file.stream_position()?;
+        let size = file.seek(SeekFrom::End(0))?;
+        if offset != size {
+            file.seek(SeekFrom::Start(offset))?;
+        }
+
+        Ok(Self::with_size(file, size, offset))
+    }
+
+    /// Creates a new `ExactReader` with a known size, the inner reader must be at `offset`.
+    fn with_size(file: R, size: u64, offset: u64) -> Self {
         Self {
             file,
-            size,
-            buffer: VecDeque::new(),
-            file_offset_view: 0..=0,
-            buffer_offset: 0,
-            seeked: None,
+            state: BufferState::new(size, offset),
         }
     }
-}
 
-impl<R: Read + Seek> ExactReader<R> {
     /// The total size of the file(s) in bytes.
-    pub fn size(&self) -> usize {
-        self.size
+    pub fn size(&self) -> u64 {
+        self.state.size
+    }
+
+    /// Limits the buffer size, evicting consumed bytes when a reservation would exceed it.
+    ///
+    /// The limit is soft: a single reservation larger than the limit is still served as a whole.
+    /// `None` (the default) lets the buffer grow without a limit.
+    pub fn set_max_buffer_size(&mut self, max_buffer_size: Option<usize>) {
+        self.state.max_buffer_size = max_buffer_size;
+    }
+
+    /// Keeps the last `backlog` consumed bytes on eviction, so that short seeks back stay cached.
+    pub fn set_backlog(&mut self, backlog: usize) {
+        self.state.backlog = backlog;
+    }
+
+    /// Keeps up to `max_ranges` ranges, `max_size` bytes in total, when a seek leaves the buffer.
+    ///
+    /// Seeking back into one of these ranges swaps it in as the buffer without any I/O.
+    /// The least recently used ranges are evicted first. Both limits are `0` by default.
+    pub fn set_cache_limits(&mut self, max_ranges: usize, max_size: usize) {
+        self.state.cache.set_limits(max_ranges, max_size);
+    }
+
+    /// The I/O counters since the creation of the reader or the last [`ExactReader::reset_stats`].
+    pub fn stats(&self) -> Stats {
+        self.state.stats
     }
 
-    /// Calculates the physical index within the file(s) from the current buffer offset.
-    #[inline]
-    fn physical_idx(&self) -> usize {
-        self.file_offset_view.start() + self.buffer_offset
+    /// Resets the I/O counters.
+    pub fn reset_stats(&mut self) {
+        self.state.stats = Stats::default();
     }
 
-    /// Reads the given range from the inner file(s).
-    fn _read(&mut self, buf: &mut Vec<u8>, read_size: usize, head: usize, tail: usize) {
-        let _ = self.file.by_ref().take(read_size as u64).read_to_end(buf);
-        self.file_offset_view = head..=tail;
+    /// Reads the range of the fetch from the inner file(s) straight into the buffer.
+    fn fetch(&mut self, fetch: Fetch) -> std::io::Result<()> {
+        // an empty fetch only resolves a pending seek, the inner reader is left untouched
+        if fetch.len() != 0 && self.state.inner_offset != fetch.offset() {
+            self.state.stats.seeks += 1;
+            self.state.inner_offset = self.file.seek(SeekFrom::Start(fetch.offset()))?;
+        }
+
+        self.state.begin(fetch);
+        while let Some(space) = self.state.pending_space() {
+            let result = self.file.read(space);
+            self.state.stats.reads += 1;
+            match result {
+                Ok(0) => break,
+                Ok(read) => self.state.advance(read),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.state.abort();
+                    return Err(err);
+                }
+            }
+        }
+        self.state.finish();
+
+        Ok(())
     }
 
     /// Reserves and caches space in the buffer for future reads
+    ///
+    /// I/O errors are ignored here, they are reported by the next read instead.
+    /// Use [`ExactReader::try_reserve`] to handle them right away.
     pub fn reserve(&mut self, reserve_size: usize) {
-        let real_head = self.file_offset_view.start();
+        let _ = self.try_reserve(reserve_size);
+    }
+
+    /// Reserves and caches space in the buffer for future reads,
+    /// returning the number of bytes cached from the current offset.
+    ///
+    /// The returned value is less than `reserve_size` only when the end of the file(s) is reached.
+    pub fn try_reserve(&mut self, reserve_size: usize) -> std::io::Result<usize> {
+        if let Some(fetch) = self.state.seek_fetch(reserve_size) {
+            self.fetch(fetch)?;
+        }
+        if let Some(fetch) = self.state.back_fetch(reserve_size) {
+            self.fetch(fetch)?;
+        }
+
+        Ok(self.state.reserved(reserve_size))
+    }
+
+    /// Reserves and caches the bytes before the current offset for backward reads
+    ///
+    /// I/O errors are ignored here, they are reported by the next read instead.
+    /// Use [`ExactReader::try_reserve_back`] to handle them right away.
+    pub fn reserve_back(&mut self, reserve_size: usize) {
+        let _ = self.try_reserve_back(reserve_size);
+    }
+
+    /// Reserves and caches the bytes before the current offset for backward reads,
+    /// returning the number of bytes cached before the current offset.
+    ///
+    /// The missing bytes are read in front of the buffer with a single fetch, so reading backward
+    /// in chunks keeps the offset and the cached data instead of seeking for every chunk.
+    /// The returned value is less than `reserve_size` only when the start of the file(s) is reached.
+    pub fn try_reserve_back(&mut self, reserve_size: usize) -> std::io::Result<usize> {
+        if let Some(fetch) = self.state.seek_fetch_back(reserve_size) {
+            self.fetch(fetch)?;
+        }
+        if let Some(fetch) = self.state.front_fetch(reserve_size) {
+            self.fetch(fetch)?;
+        }
+
+        Ok(self.state.reserved_back(reserve_size))
+    }
+
+    /// Returns the next `peek_size` bytes without advancing the offset.
+    ///
+    /// The data is split in two slices when it wraps around the ring buffer,
+    /// the slices are shorter than `peek_size` only at the end of the file(s).
+    pub fn peek(&mut self, peek_size: usize) -> std::io::Result<(&[u8], &[u8])> {
+        let size = self.try_reserve(peek_size)?;
+        Ok(self.state.unread(size))
+    }
+
+    /// Returns the next `peek_size` bytes as a single slice without advancing the offset.
+    ///
+    /// The buffer is rearranged only when the data wraps around the ring buffer.
+    pub fn peek_contiguous(&mut self, peek_size: usize) -> std::io::Result<&[u8]> {
+        let size = self.try_reserve(peek_size)?;
+        Ok(self.state.unread_contiguous(size))
+    }
+
+    /// Returns the distance from the current offset to the first `byte`, without advancing the offset.
+    ///
+    /// See [`ExactReader::find`].
+    pub fn find_byte(&mut self, byte: u8) -> std::io::Result<Option<usize>> {
+        self.find(&[byte])
+    }
+
+    /// Returns the distance from the current offset to the first `needle`, without advancing the offset.
+    ///
+    /// The reservation is extended in chunks until the needle is found or the end of the file(s)
+    /// is reached, so everything up to the match stays cached. Matches straddling the wrap of the
+    /// ring buffer or the boundary of two files are found as well.
+    pub fn find(&mut self, needle: &[u8]) -> std::io::Result<Option<usize>> {
+        self.try_reserve(DEFAULT_RESERVE_SIZE.max(needle.len()))?;
+        let mut reserved = self.state.reserved(usize::MAX);
+        let mut searched = 0;
+
+        loop {
+            let (head, tail) = self.state.unread(reserved);
+            if let Some(pos) = find_pattern(head, tail, needle, searched) {
+                return Ok(Some(pos));
+            }
+
+            // the last bytes may start a match completed by the next chunk
+            searched = (reserved + 1).saturating_sub(needle.len());
+            let extended = self.try_reserve(reserved + DEFAULT_RESERVE_SIZE)?;
+            if extended == reserved {
+                return Ok(None);
+            }
+            reserved = extended;
+        }
+    }
+
+    /// Returns the distance back from the current offset to the start of the last `needle`
+    /// ending at or before it, without moving the offset.
+    ///
+    /// The backward reservation is extended in chunks until the needle is found or the start
+    /// of the file(s) is reached, as [`ExactReader::find`] does forward.
+    pub fn rfind(&mut self, needle: &[u8]) -> std::io::Result<Option<usize>> {
+        self.rfind_from(needle, 0)
+    }
+
+    /// Same as `rfind`, ignoring the last `skip` bytes before the current offset.
+    fn rfind_from(&mut self, needle: &[u8], skip: usize) -> std::io::Result<Option<usize>> {
+        let mut reserved = self.try_reserve_back(DEFAULT_RESERVE_SIZE.max(needle.len() + skip))?;
+        let mut searched = skip;
+
+        loop {
+            // the buffer indices shift with every fetch in front, the distances do not
+            let cursor = self.state.reserved_back(usize::MAX);
+            if searched <= reserved {
+                let start = cursor - reserved;
+                let (head, tail) = self.state.slices(start..cursor - searched);
+                if let Some(pos) = rfind_pattern(head, tail, needle) {
+                    return Ok(Some(reserved - pos));
+                }
+            }
+
+            // the first bytes may end a match started in the previous chunk
+            searched = (reserved + 1).saturating_sub(needle.len()).max(skip);
+            let extended = self.try_reserve_back(reserved + DEFAULT_RESERVE_SIZE)?;
+            if extended == reserved {
+                return Ok(None);
+            }
+            reserved = extended;
+        }
+    }
+
+    /// Reads the line ending right before the current offset, appending it to `out`
+    /// and moving the offset back to its start.
+    ///
+    /// As with `BufRead::read_line`, the line is appended in order with its `\n`,
+    /// and the number of bytes read is returned, `0` at the start of the file(s).
+    /// Calling it repeatedly yields the lines from the last to the first, as `tac` does.
+    pub fn read_line_back(&mut self, out: &mut Vec<u8>) -> std::io::Result<usize> {
+        // the `\n` right before the offset ends the line instead of starting it
+        let size = match self.rfind_from(b"\n", 1)? {
+            Some(distance) => distance - 1,
+            None => self.state.reserved_back(usize::MAX),
+        };
 
-        if let Some(seek_head) = self.seeked.take() {
-            let seek_tail = seek_head + reserve_size;
+        let cursor = self.state.reserved_back(usize::MAX);
+        let (head, tail) = self.state.slices(cursor - size..cursor);
+        out.extend_from_slice(head);
+        out.extend_from_slice(tail);
+        self.state.consume_back(size);
 
-            if self.file_offset_view.contains(&seek_head) {
-                self.buffer_offset = seek_head - real_head;
-            } else if self.file_offset_view.contains(&seek_tail) {
-                let read_size = self.file_offset_view.start() - seek_head;
-                let mut buf: Vec<u8> = Vec::with_capacity(read_size); // TODO: make it zero copy
+        Ok(size)
+    }
 
-                self._read(&mut buf, read_size, seek_head, seek_tail);
-                self.buffer_offset = 0;
+    /// Reads all bytes until the `needle` is found or the end of the file(s), appending them to `out`.
+    ///
+    /// As with `BufRead::read_until`, the needle is included, and the number of bytes read is returned.
+    pub fn read_until_pattern(
+        &mut self,
+        needle: &[u8],
+        out: &mut Vec<u8>,
+    ) -> std::io::Result<usize> {
+        let size = match self.find(needle)? {
+            Some(pos) => pos + needle.len(),
+            None => self.state.reserved(usize::MAX),
+        };
 
-                self.buffer.extend_front(buf.as_slice());
-                return;
+        let (head, tail) = self.state.unread(size);
+        out.extend_from_slice(head);
+        out.extend_from_slice(tail);
+        self.state.consume(size);
+
+        Ok(size)
+    }
+}
+
+impl<R: Read + Seek + ReadAt> ExactReader<R> {
+    /// Reads into `buf` from the given offset, without moving the offset of the reader.
+    ///
+    /// The data is copied from the buffer or the cached ranges when possible,
+    /// otherwise it is read from the inner file(s) with [`ReadAt::read_at`], bypassing the buffer.
+    /// As with `Read::read`, fewer bytes than requested may be read, and `0` means the end.
+    pub fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(size) = self.state.read_cached_at(offset, buf) {
+            return Ok(size);
+        }
+
+        let read = self.file.read_at(offset, buf)?;
+        self.state.stats.reads += 1;
+        self.state.stats.bytes_requested += buf.len() as u64;
+        self.state.stats.bytes_read += read as u64;
+
+        Ok(read)
+    }
+
+    /// Reads exactly `buf.len()` bytes from the given offset, without moving the offset of the reader.
+    ///
+    /// Fails with `UnexpectedEof` when the end of the file(s) is reached first.
+    pub fn read_exact_at(&mut self, mut offset: u64, mut buf: &mut [u8]) -> std::io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(offset, buf) {
+                Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => {
+                    buf = &mut buf[read..];
+                    offset += read as u64;
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
             }
-            let mut buf: Vec<u8> = Vec::with_capacity(reserve_size); // TODO: make it zero copy
-            self._read(&mut buf, reserve_size, seek_head, seek_tail);
+        }
 
-            self.buffer_offset = 0;
+        Ok(())
+    }
+}
 
-            self.buffer.clear();
-            self.buffer.extend_back(buf.as_slice());
+impl<R: Read + Seek + Send + 'static> ExactReader<R> {
+    /// Moves the inner reader to a worker thread that reads `read_ahead` bytes
+    /// past each fetch in the background, so that sequential reservations rarely wait for I/O.
+    ///
+    /// The buffer and its state are kept.
+    pub fn with_prefetch(self, read_ahead: usize) -> ExactReader<Prefetcher<R>> {
+        let position = self.state.inner_offset;
+        let file = Prefetcher::spawn(
+            self.file,
+            self.state.inner_offset,
+            position,
+            self.state.size,
+            read_ahead,
+        );
 
-            return;
+        ExactReader {
+            file,
+            state: self.state,
         }
+    }
+}
+
+/// Generates the typed read and peek helpers from `from_*_bytes` conversions.
+macro_rules! typed_helpers {
+    ($($ty:ty => $read:ident, $peek:ident, $from:ident;)*) => {
+        $(
+            #[doc = concat!("Reads a `", stringify!($ty), "` using `", stringify!($ty), "::", stringify!($from), "`.")]
+            #[inline]
+            pub fn $read(&mut self) -> std::io::Result<$ty> {
+                self.read_array().map(<$ty>::$from)
+            }
+
+            #[doc = concat!("Peeks a `", stringify!($ty), "` using `", stringify!($ty), "::", stringify!($from), "`, without advancing the offset.")]
+            #[inline]
+            pub fn $peek(&mut self) -> std::io::Result<$ty> {
+                self.peek_array().map(<$ty>::$from)
+            }
+        )*
+    };
+}
 
-        if self.buffer.len() >= self.buffer_offset + reserve_size {
-            return;
+impl<R: Read + Seek> ExactReader<R> {
+    /// Peeks the next `N` bytes as an array, without advancing the offset.
+    ///
+    /// Fails with `UnexpectedEof` when fewer than `N` bytes are left.
+    pub fn peek_array<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        let (head, tail) = self.peek(N)?;
+        if head.len() + tail.len() < N {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
         }
 
-        let mut buf: Vec<u8> = Vec::with_capacity(reserve_size); // TODO: make it zero copy
-        let tail = self.file_offset_view.start() + self.buffer.len() + buf.len();
-        self._read(&mut buf, reserve_size, *self.file_offset_view.start(), tail);
+        let mut array = [0u8; N];
+        array[..head.len()].copy_from_slice(head);
+        array[head.len()..].copy_from_slice(tail);
+
+        Ok(array)
+    }
+
+    /// Reads the next `N` bytes as an array.
+    ///
+    /// Fails with `UnexpectedEof` when fewer than `N` bytes are left, without advancing the offset.
+    pub fn read_array<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        let array = self.peek_array()?;
+        self.state.consume(N);
+
+        Ok(array)
+    }
+
+    typed_helpers! {
+        u8 => read_u8, peek_u8, from_le_bytes;
+        i8 => read_i8, peek_i8, from_le_bytes;
+
+        u16 => read_u16_le, peek_u16_le, from_le_bytes;
+        u32 => read_u32_le, peek_u32_le, from_le_bytes;
+        u64 => read_u64_le, peek_u64_le, from_le_bytes;
+        u128 => read_u128_le, peek_u128_le, from_le_bytes;
+        i16 => read_i16_le, peek_i16_le, from_le_bytes;
+        i32 => read_i32_le, peek_i32_le, from_le_bytes;
+        i64 => read_i64_le, peek_i64_le, from_le_bytes;
+        i128 => read_i128_le, peek_i128_le, from_le_bytes;
+        f32 => read_f32_le, peek_f32_le, from_le_bytes;
+        f64 => read_f64_le, peek_f64_le, from_le_bytes;
 
-        self.buffer.extend_back(buf.as_mut_slice());
+        u16 => read_u16_be, peek_u16_be, from_be_bytes;
+        u32 => read_u32_be, peek_u32_be, from_be_bytes;
+        u64 => read_u64_be, peek_u64_be, from_be_bytes;
+        u128 => read_u128_be, peek_u128_be, from_be_bytes;
+        i16 => read_i16_be, peek_i16_be, from_be_bytes;
+        i32 => read_i32_be, peek_i32_be, from_be_bytes;
+        i64 => read_i64_be, peek_i64_be, from_be_bytes;
+        i128 => read_i128_be, peek_i128_be, from_be_bytes;
+        f32 => read_f32_be, peek_f32_be, from_be_bytes;
+        f64 => read_f64_be, peek_f64_be, from_be_bytes;
     }
 }
 
 impl<R: Read + Seek> Read for ExactReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        // TODO: read when size > file.size
-        let size = buf.len();
-        self.reserve(size);
-
-        let (head, tail) = self.buffer.as_slices();
-        let head_len = head.len();
-        let adjusted_head_len = head_len.saturating_sub(self.buffer_offset);
-        let tail_offset = self.buffer_offset.saturating_sub(head_len);
-
-        if adjusted_head_len == 0 {
-            // The buffer_offset is in the tail slice
-            buf.copy_from_slice(&tail[tail_offset..tail_offset + size]);
-        } else if adjusted_head_len >= size {
-            // The data is entirely in the head slice
-            buf.copy_from_slice(&head[self.buffer_offset..self.buffer_offset + size]);
-        } else {
-            // Data spans both head and tail slices
-            buf[..adjusted_head_len].copy_from_slice(&head[self.buffer_offset..]);
-            buf[adjusted_head_len..]
-                .copy_from_slice(&tail[tail_offset..tail_offset + size - adjusted_head_len]);
-        }
-        self.buffer_offset += size;
+        self.try_reserve(buf.len())?;
+        Ok(self.state.read_buffered(buf))
+    }
 
-        Ok(size)
+    /// Fills the slices in order from a single reservation of their total length.
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<usize> {
+        let total = bufs.iter().map(|buf| buf.len()).sum();
+        self.try_reserve(total)?;
+
+        let mut taken = 0;
+        for buf in bufs {
+            let read = self.state.read_buffered(buf);
+            taken += read;
+            if read < buf.len() {
+                break;
+            }
+        }
+
+        Ok(taken)
     }
 }
 
-impl<R: Read + Seek> Seek for ExactReader<R> {
-    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
-        let calculated_seek = calculate_seek(self.size, self.physical_idx(), pos)? as usize;
-        if self.file_offset_view.contains(&calculated_seek) {
-            self.buffer_offset = calculated_seek - self.file_offset_view.start();
-            return Ok(calculated_seek as u64);
+impl<R: Read + Seek> BufRead for ExactReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        // only go to the file(s) when nothing is left from the reservations
+        if self.state.is_exhausted() {
+            self.try_reserve(DEFAULT_RESERVE_SIZE)?;
         }
 
-        let result = self.file.seek(pos)?;
-        self.seeked = Some(result as usize);
+        Ok(self.state.unread_slices().0)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.state.consume(amt);
+    }
+}
 
-        Ok(result)
+impl<R: Read + Seek> Seek for ExactReader<R> {
+    /// Moves the offset, the inner file(s) are only moved by the next reservation,
+    /// which reports their errors.
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.state.seek(pos)
     }
 
     fn stream_position(&mut self) -> std::io::Result<u64> {
-        Ok(self.physical_idx() as u64)
+        Ok(self.state.physical_idx())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn new_file(data: &[u8]) -> File<Cursor<Vec<u8>>> {
+        File {
+            file: Cursor::new(data.to_vec()),
+            size: data.len() as u64,
+            filename: "cursor".to_string(),
+        }
+    }
+
+    fn new_reader(data: Vec<u8>) -> ExactReader<File<Cursor<Vec<u8>>>> {
+        ExactReader::new_single(new_file(&data))
+    }
+
+    fn new_multi_reader(parts: &[&[u8]]) -> ExactReader<MultiFile<Cursor<Vec<u8>>>> {
+        ExactReader::new_multi(MultiFile::new(
+            parts.iter().map(|part| new_file(part)).collect(),
+        ))
+    }
+
+    /// Creates a reader at offset 2 whose cached data wraps around the ring buffer.
+    fn new_wrapped_reader(data: Vec<u8>) -> ExactReader<File<Cursor<Vec<u8>>>> {
+        let mut reader = new_reader(data);
+
+        reader.seek(SeekFrom::Start(4)).unwrap();
+        reader.reserve(4);
+        // the seek back puts the head of the data at the end of the ring buffer
+        reader.seek(SeekFrom::Start(2)).unwrap();
+        reader.reserve(4);
+        assert!(!reader.state.unread_slices().1.is_empty());
+
+        reader
+    }
+
+    /// A reader that counts the reads of the inner reader.
+    struct Counting<R> {
+        inner: R,
+        reads: std::rc::Rc<std::cell::Cell<usize>>,
+        bytes: std::rc::Rc<std::cell::Cell<usize>>,
+    }
+
+    impl<R: Read> Read for Counting<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let read = self.inner.read(buf)?;
+            self.reads.set(self.reads.get() + 1);
+            self.bytes.set(self.bytes.get() + read);
+            Ok(read)
+        }
+    }
+
+    impl<R: Seek> Seek for Counting<R> {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    /// A reader that fails on every read.
+    struct Broken;
+
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    impl Seek for Broken {
+        fn seek(&mut self, _: SeekFrom) -> std::io::Result<u64> {
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn test_try_reserve() {
+        let mut reader = new_reader(vec![1u8, 2, 3, 4, 5]);
+
+        assert_eq!(reader.try_reserve(2).unwrap(), 2);
+        assert_eq!(reader.try_reserve(10).unwrap(), 5);
+
+        reader.seek(SeekFrom::Start(3)).unwrap();
+        assert_eq!(reader.try_reserve(10).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_try_reserve_error() {
+        let mut reader = ExactReader::new_single(File {
+            file: Broken,
+            size: 10,
+            filename: "broken".to_string(),
+        });
+
+        let err = reader.try_reserve(4).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+
+        let mut buf = [0u8; 4];
+        let err = reader.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_seek_back() {
+        let mut reader = new_reader((0u8..10).collect());
+
+        reader.seek(SeekFrom::Start(5)).unwrap();
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [5, 6]);
+
+        reader.seek(SeekFrom::Start(3)).unwrap();
+        let mut buf = [0u8; 6];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_new() {
+        let mut cursor = Cursor::new((0u8..10).collect::<Vec<_>>());
+        cursor.set_position(4);
+
+        let mut reader = ExactReader::new(cursor).unwrap();
+        assert_eq!(reader.size(), 10);
+        assert_eq!(reader.stream_position().unwrap(), 4);
+
+        let mut buf = [0u8; 3];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [4, 5, 6]);
+
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_buf_read() {
+        let mut reader = new_reader(b"first\nsecond\nthird".to_vec());
+
+        let lines: Vec<String> = reader.by_ref().lines().map(Result::unwrap).collect();
+        assert_eq!(lines, ["first", "second", "third"]);
+
+        reader.seek(SeekFrom::Start(6)).unwrap();
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line).unwrap();
+        assert_eq!(line, b"second\n");
+    }
+
+    #[test]
+    fn test_fill_buf_reserved() {
+        let mut reader = new_reader((0u8..100).collect());
+
+        reader.reserve(4);
+        assert_eq!(reader.fill_buf().unwrap(), [0, 1, 2, 3]);
+
+        reader.consume(3);
+        assert_eq!(reader.fill_buf().unwrap(), [3]);
+
+        reader.consume(1);
+        assert_eq!(reader.fill_buf().unwrap().len(), 96);
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut reader = new_wrapped_reader((0u8..10).collect());
+
+        let (head, tail) = reader.peek(4).unwrap();
+        assert_eq!([head, tail].concat(), [2, 3, 4, 5]);
+        assert_eq!(reader.peek_contiguous(4).unwrap(), [2, 3, 4, 5]);
+        assert_eq!(reader.stream_position().unwrap(), 2);
+
+        reader.seek(SeekFrom::End(-1)).unwrap();
+        assert_eq!(reader.peek_contiguous(4).unwrap(), [9]);
+
+        let mut buf = [0u8; 1];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [9]);
+    }
+
+    #[test]
+    fn test_max_buffer_size() {
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let mut reader = new_reader(data.clone());
+        reader.set_max_buffer_size(Some(64));
+        reader.set_backlog(16);
+
+        let mut buf = [0u8; 10];
+        for chunk in data.chunks(10) {
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, chunk);
+            assert!(reader.state.buffer.len() <= 64);
+        }
+
+        // the backlog is still cached
+        reader.seek(SeekFrom::Current(-16)).unwrap();
+        assert!(reader.state.seeked.is_none());
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[984..994]);
+    }
+
+    #[test]
+    fn test_range_cache() {
+        let data: Vec<u8> = (0..=255).collect();
+        let reads = std::rc::Rc::new(std::cell::Cell::new(0));
+        let mut reader = ExactReader::new(Counting {
+            inner: Cursor::new(data.clone()),
+            reads: reads.clone(),
+            bytes: Default::default(),
+        })
+        .unwrap();
+        reader.set_cache_limits(4, 1024);
+
+        let mut buf = [0u8; 16];
+        for offset in [200, 0, 100, 200, 0, 100] {
+            reader.seek(SeekFrom::Start(offset)).unwrap();
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, data[offset as usize..offset as usize + 16]);
+        }
+
+        // only the first visit of each range goes to the inner reader
+        let cold = reads.get();
+        for offset in [0, 200, 100] {
+            reader.seek(SeekFrom::Start(offset)).unwrap();
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, data[offset as usize..offset as usize + 16]);
+        }
+        assert_eq!(reads.get(), cold);
+    }
+
+    #[test]
+    fn test_partial_overlap() {
+        let data: Vec<u8> = (0..=255).collect();
+        let bytes = std::rc::Rc::new(std::cell::Cell::new(0));
+        let mut reader = ExactReader::new(Counting {
+            inner: Cursor::new(data.clone()),
+            reads: Default::default(),
+            bytes: bytes.clone(),
+        })
+        .unwrap();
+
+        reader.seek(SeekFrom::Start(100)).unwrap();
+        reader.reserve(10);
+        assert_eq!(bytes.get(), 10);
+
+        // covers the buffer on both sides
+        reader.seek(SeekFrom::Start(90)).unwrap();
+        assert_eq!(reader.try_reserve(30).unwrap(), 30);
+        assert_eq!(bytes.get(), 30);
+
+        // goes past the tail
+        reader.seek(SeekFrom::Start(115)).unwrap();
+        assert_eq!(reader.try_reserve(10).unwrap(), 10);
+        assert_eq!(bytes.get(), 35);
+
+        let mut buf = [0u8; 35];
+        reader.seek(SeekFrom::Start(90)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[90..125]);
+        assert_eq!(bytes.get(), 35);
+    }
+
+    #[test]
+    fn test_typed() {
+        let mut reader = new_reader(vec![
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x00, 0x80, 0x3f, 0xff,
+        ]);
+
+        assert_eq!(reader.peek_u32_le().unwrap(), 0x04030201);
+        assert_eq!(reader.read_u16_be().unwrap(), 0x0102);
+        assert_eq!(reader.read_u16_le().unwrap(), 0x0403);
+        assert_eq!(reader.read_array::<4>().unwrap(), [5, 6, 7, 8]);
+        assert_eq!(reader.read_f32_le().unwrap(), 1.0);
+        assert_eq!(reader.read_i8().unwrap(), -1);
+
+        let err = reader.read_u8().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_typed_wrapped() {
+        let mut reader = new_wrapped_reader((0u8..16).collect());
+
+        assert_eq!(reader.read_u64_be().unwrap(), 0x0203040506070809);
+        assert_eq!(reader.peek_u8().unwrap(), 10);
+        assert_eq!(reader.stream_position().unwrap(), 10);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut reader = new_reader((0u8..100).collect());
+
+        let mut buf = [0u8; 10];
+        reader.read_exact(&mut buf).unwrap();
+        reader.seek(SeekFrom::Start(2)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        reader.seek(SeekFrom::Start(95)).unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 5);
+
+        let stats = reader.stats();
+        assert_eq!(stats.seek_hits, 1);
+        assert_eq!(stats.seek_misses, 1);
+        assert_eq!(stats.seeks, 1);
+        assert_eq!(stats.reads, 3);
+        assert_eq!(stats.bytes_requested, 17);
+        assert_eq!(stats.bytes_read, 17);
+        assert_eq!(stats.bytes_served, 25);
+        assert_eq!(stats.buffer_clears, 1);
+
+        reader.reset_stats();
+        assert_eq!(reader.stats(), Stats::default());
+    }
+
+    #[test]
+    fn test_seek_overflow() {
+        let mut reader = new_reader(vec![1u8, 2, 3, 4, 5]);
+
+        let err = reader.seek(SeekFrom::End(-6)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        assert_eq!(reader.seek(SeekFrom::Start(u64::MAX)).unwrap(), u64::MAX);
+        let err = reader.seek(SeekFrom::Current(1)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(reader.try_reserve(4).unwrap(), 0);
+
+        assert_eq!(
+            reader.seek(SeekFrom::Current(i64::MIN)).unwrap(),
+            u64::MAX >> 1
+        );
+    }
+
+    #[test]
+    fn test_read_at() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut reader = new_reader(data.clone());
+
+        reader.seek(SeekFrom::Start(10)).unwrap();
+        reader.reserve(20);
+        let before = reader.stats();
+
+        // the cached part is copied, the rest is read at the offset
+        let mut buf = [0u8; 30];
+        reader.read_exact_at(20, &mut buf).unwrap();
+        assert_eq!(buf, data[20..50]);
+        assert_eq!(reader.stats().bytes_read - before.bytes_read, 20);
+
+        let err = reader.read_exact_at(250, &mut buf).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // the cursor is left where it was
+        assert_eq!(reader.stream_position().unwrap(), 10);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[10..40]);
+    }
+
+    #[test]
+    fn test_read_vectored() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut reader = new_reader(data.clone());
+
+        let (mut a, mut b) = ([0u8; 3], [0u8; 5]);
+        let read = reader
+            .read_vectored(&mut [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)])
+            .unwrap();
+        assert_eq!(read, 8);
+        assert_eq!([&a[..], &b[..]].concat(), data[..8]);
+        assert_eq!(reader.stats().reads, 1);
+
+        reader.seek(SeekFrom::End(-4)).unwrap();
+        let read = reader
+            .read_vectored(&mut [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)])
+            .unwrap();
+        assert_eq!(read, 4);
+        assert_eq!([&a[..], &b[..1]].concat(), data[252..]);
+    }
+
+    #[test]
+    fn test_find() {
+        let mut reader = new_wrapped_reader(b"0123456789".to_vec());
+
+        assert_eq!(reader.find(b"345").unwrap(), Some(1));
+        assert_eq!(reader.find_byte(b'9').unwrap(), Some(7));
+        assert_eq!(reader.find(b"90").unwrap(), None);
+        assert_eq!(reader.stream_position().unwrap(), 2);
+
+        let mut out = Vec::new();
+        assert_eq!(reader.read_until_pattern(b"56", &mut out).unwrap(), 5);
+        assert_eq!(out, b"23456");
+        out.clear();
+        assert_eq!(reader.read_until_pattern(b"xx", &mut out).unwrap(), 3);
+        assert_eq!(out, b"789");
+    }
+
+    #[test]
+    fn test_find_multi() {
+        let mut filler = vec![b'.'; DEFAULT_RESERVE_SIZE - 2];
+        filler.extend_from_slice(b"<re");
+        let mut reader = new_multi_reader(&[&filler, b"cord>tail"]);
+
+        // the marker straddles both the file boundary and the first chunk
+        let pos = DEFAULT_RESERVE_SIZE - 2;
+        assert_eq!(reader.find(b"<record>").unwrap(), Some(pos));
+
+        let mut out = Vec::new();
+        reader.read_until_pattern(b"<record>", &mut out).unwrap();
+        assert_eq!(out.len(), pos + 8);
+        assert_eq!(reader.find_byte(b'l').unwrap(), Some(3));
+    }
+
+    #[test]
+    fn test_reserve_back() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut reader = new_reader(data.clone());
+
+        reader.seek(SeekFrom::End(0)).unwrap();
+        assert_eq!(reader.try_reserve_back(100).unwrap(), 100);
+        assert_eq!(reader.try_reserve_back(300).unwrap(), 256);
+        assert_eq!(reader.stream_position().unwrap(), 256);
+
+        // the chunks before the offset are added to the buffer, only the seek to the end missed
+        let stats = reader.stats();
+        assert_eq!(stats.reads, 2);
+        assert_eq!(stats.bytes_read, 256);
+        assert_eq!(stats.buffer_clears, 1);
+        assert_eq!(stats.seek_misses, 1);
+
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        let mut buf = [0u8; 256];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[..]);
+        assert_eq!(reader.stats().reads, 2);
+        assert_eq!(reader.stats().seek_hits, 1);
+    }
+
+    #[test]
+    fn test_reserve_back_after_seek() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut reader = new_reader(data.clone());
+        reader.try_reserve(100).unwrap();
+
+        // the gap up to the offset is read after the buffer, which is kept
+        reader.seek(SeekFrom::Start(150)).unwrap();
+        assert_eq!(reader.try_reserve_back(100).unwrap(), 100);
+        let stats = reader.stats();
+        assert_eq!(stats.bytes_read, 150);
+        assert_eq!(stats.buffer_clears, 0);
+
+        reader.seek(SeekFrom::Start(20)).unwrap();
+        assert_eq!(reader.read_array::<2>().unwrap(), [20, 21]);
+        assert_eq!(reader.stats().seek_hits, 1);
+
+        // nothing is fetched before the start
+        reader.seek(SeekFrom::Start(200)).unwrap();
+        reader.try_reserve(10).unwrap();
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(reader.try_reserve_back(10).unwrap(), 0);
+        assert_eq!(reader.stats().buffer_clears, 1);
+        assert_eq!(reader.read_array::<2>().unwrap(), [0, 1]);
+    }
+
+    #[test]
+    fn test_rfind() {
+        let mut tail = b"ter>".to_vec();
+        tail.extend(vec![b'.'; DEFAULT_RESERVE_SIZE]);
+        let mut reader = new_multi_reader(&[b"head<foo", &tail]);
+
+        // the marker straddles both the file boundary and the first chunk
+        reader.seek(SeekFrom::End(0)).unwrap();
+        let distance = DEFAULT_RESERVE_SIZE + 8;
+        assert_eq!(reader.rfind(b"<footer>").unwrap(), Some(distance));
+        assert_eq!(reader.rfind(b"<none>").unwrap(), None);
+
+        reader.seek(SeekFrom::Current(-(distance as i64))).unwrap();
+        assert_eq!(reader.read_array::<8>().unwrap(), *b"<footer>");
+        assert_eq!(reader.rfind(b"<footer>").unwrap(), Some(8));
+        assert_eq!(reader.rfind(b"ter").unwrap(), Some(4));
+    }
+
+    #[test]
+    fn test_read_line_back() {
+        let mut reader = new_multi_reader(&[b"first\nsec", b"ond\n\nlast\n"]);
+        reader.seek(SeekFrom::End(0)).unwrap();
+
+        let mut lines = Vec::new();
+        loop {
+            let mut line = Vec::new();
+            if reader.read_line_back(&mut line).unwrap() == 0 {
+                break;
+            }
+            lines.push(line);
+        }
+        assert_eq!(lines, [&b"last\n"[..], b"\n", b"second\n", b"first\n"]);
+        assert_eq!(reader.stream_position().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_update_files() {
+        let mut reader = new_multi_reader(&[b"abc"]);
+
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(reader.size(), 3);
+
+        // a growing set of segments is read from where the last read stopped
+        reader.update_files(|files| files.push_file(new_file(b"def")));
+        assert_eq!(reader.size(), 6);
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"abcdef");
+
+        let position = reader.stream_position().unwrap();
+        assert_eq!(reader.files().locate(position), Some((1, "cursor", 3)));
+
+        reader.update_files(|files| files.remove_file(0)).unwrap();
+        assert_eq!(reader.size(), 3);
+        assert_eq!(reader.read(&mut [0u8; 1]).unwrap(), 0);
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(reader.read_array::<3>().unwrap(), *b"def");
+    }
+
+    #[test]
+    fn test_read_eof() {
+        let mut reader = new_reader(vec![1u8, 2, 3, 4, 5]);
+
+        let mut buf = [0u8; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [1, 2, 3, 4]);
+
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 5);
+
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+        reader.seek(SeekFrom::End(-2)).unwrap();
+        let err = reader.read_exact(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_read_to_end() {
+        let data: Vec<u8> = (0u8..255).collect();
+        let mut reader = new_reader(data.clone());
+
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+
+        reader.seek(SeekFrom::Start(100)).unwrap();
+        let mut out = Vec::new();
+        std::io::copy(&mut reader, &mut out).unwrap();
+        assert_eq!(out, data[100..]);
+    }
+
+    #[test]
+    fn test_seek_past_end() {
+        // seeks are lazy whatever the inner reader, reading past the end gives nothing
+        let mut reader = new_multi_reader(&[b"abc"]);
+        assert_eq!(reader.seek(SeekFrom::Start(10)).unwrap(), 10);
+        assert_eq!(reader.read(&mut [0u8; 4]).unwrap(), 0);
+
+        // a seek back that is still past the end gives nothing as well
+        assert_eq!(reader.seek(SeekFrom::End(2)).unwrap(), 5);
+        assert_eq!(reader.read(&mut [0u8; 4]).unwrap(), 0);
+        assert_eq!(reader.seek(SeekFrom::End(1)).unwrap(), 4);
+        assert_eq!(reader.read(&mut [0u8; 4]).unwrap(), 0);
+
+        reader.seek(SeekFrom::Start(1)).unwrap();
+        assert_eq!(reader.read_array::<2>().unwrap(), *b"bc");
     }
 }
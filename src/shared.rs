@@ -0,0 +1,336 @@
+use std::{
+    io::{BufRead, Read, Seek, SeekFrom},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    multifile::{File, MultiFile},
+    read_at::ReadAt,
+    utils::calculate_seek,
+};
+
+/// The default size of the cached blocks.
+pub const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
+/// The default number of cached blocks.
+pub const DEFAULT_MAX_BLOCKS: usize = 256;
+/// The maximum number of independently locked shards of the block cache.
+const SHARDS: usize = 16;
+
+/// A cached block of the file(s).
+type Block = Arc<[u8]>;
+
+/// A shard of the block cache, holding the blocks whose index falls into it.
+struct Shard {
+    /// The cached blocks by index, from the least to the most recently used.
+    blocks: Vec<(u64, Block)>,
+    /// The maximum number of cached blocks.
+    max_blocks: usize,
+}
+
+/// The state shared by the handles of a `SharedExactReader`.
+struct Shared<R> {
+    /// The inner reader for the file(s), only read positionally.
+    file: R,
+    /// The size of the file(s).
+    size: u64,
+
+    /// The size of the cached blocks.
+    block_size: usize,
+    /// The block cache, locked shard by shard.
+    shards: Box<[Mutex<Shard>]>,
+}
+
+impl<R: ReadAt> Shared<R> {
+    fn new(file: R, size: u64, block_size: usize, max_blocks: usize) -> Self {
+        // the blocks are spread over the shards, so that they hold `max_blocks` in total
+        let shards = max_blocks.clamp(1, SHARDS);
+        Self {
+            file,
+            size,
+            block_size: block_size.max(1),
+            shards: (0..shards)
+                .map(|i| {
+                    Mutex::new(Shard {
+                        blocks: Vec::new(),
+                        max_blocks: max_blocks / shards + usize::from(i < max_blocks % shards),
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the block at the index, reading it from the file(s) if it is not cached.
+    ///
+    /// The shard is not locked while reading, so concurrent misses of a block may both read it.
+    fn block(&self, idx: u64) -> std::io::Result<Block> {
+        let shard = &self.shards[(idx % self.shards.len() as u64) as usize];
+
+        {
+            let mut shard = shard.lock().unwrap_or_else(|err| err.into_inner());
+            if let Some(pos) = shard.blocks.iter().position(|(i, _)| *i == idx) {
+                let entry = shard.blocks.remove(pos);
+                let block = entry.1.clone();
+                shard.blocks.push(entry);
+                return Ok(block);
+            }
+        }
+
+        let start = idx * self.block_size as u64;
+        let len = (self.size.saturating_sub(start)).min(self.block_size as u64) as usize;
+        let mut data = vec![0u8; len];
+        let mut filled = 0;
+        while filled < len {
+            match self
+                .file
+                .read_at(start + filled as u64, &mut data[filled..])
+            {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        data.truncate(filled);
+        let block: Block = data.into();
+
+        let mut shard = shard.lock().unwrap_or_else(|err| err.into_inner());
+        if shard.max_blocks > 0 {
+            shard.blocks.retain(|(i, _)| *i != idx);
+            if shard.blocks.len() >= shard.max_blocks {
+                shard.blocks.remove(0);
+            }
+            shard.blocks.push((idx, block.clone()));
+        }
+
+        Ok(block)
+    }
+}
+
+/// The `SharedExactReader` struct is a cloneable handle reading file(s) from many threads.
+///
+/// Every handle has its own position, while the file(s) and a block cache are shared.
+/// The file(s) are only read with [`ReadAt`], and the block cache is split into shards
+/// locked independently, so handles rarely wait for each other.
+pub struct SharedExactReader<R> {
+    /// The file(s) and the block cache.
+    shared: Arc<Shared<R>>,
+
+    /// The position of this handle.
+    position: u64,
+    /// The last block used by this handle, to serve sequential reads without locking.
+    current: Option<(u64, Block)>,
+}
+
+impl<R: ReadAt> SharedExactReader<MultiFile<R>> {
+    /// Creates a new `SharedExactReader` instance for reading data from multiple files.
+    pub fn new_multi(file: MultiFile<R>) -> Self {
+        let size = file.size();
+        Self::new(file, size)
+    }
+}
+
+impl<R: ReadAt> SharedExactReader<File<R>> {
+    /// Creates a new `SharedExactReader` instance for reading data from a single file.
+    pub fn new_single(file: File<R>) -> Self {
+        let size = file.size;
+        Self::new(file, size)
+    }
+}
+
+impl<R: ReadAt> SharedExactReader<R> {
+    /// Creates a new `SharedExactReader` instance for any positional reader of the given size.
+    ///
+    /// The block cache holds up to 256 blocks of 64 KiB.
+    pub fn new(file: R, size: u64) -> Self {
+        Self {
+            shared: Arc::new(Shared::new(
+                file,
+                size,
+                DEFAULT_BLOCK_SIZE,
+                DEFAULT_MAX_BLOCKS,
+            )),
+            position: 0,
+            current: None,
+        }
+    }
+
+    /// Replaces the block cache with one holding up to `max_blocks` blocks of `block_size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the handle was already cloned.
+    pub fn with_cache_limits(self, block_size: usize, max_blocks: usize) -> Self {
+        let Ok(shared) = Arc::try_unwrap(self.shared) else {
+            panic!("the cache limits are set before the reader is cloned");
+        };
+
+        Self {
+            shared: Arc::new(Shared::new(
+                shared.file,
+                shared.size,
+                block_size,
+                max_blocks,
+            )),
+            position: self.position,
+            current: None,
+        }
+    }
+
+    /// The total size of the file(s) in bytes.
+    pub fn size(&self) -> u64 {
+        self.shared.size
+    }
+
+    /// Returns the cached data from the given offset to the end of its block.
+    fn slice_at(&mut self, offset: u64) -> std::io::Result<&[u8]> {
+        let block_size = self.shared.block_size as u64;
+        let idx = offset / block_size;
+
+        if self.current.as_ref().map(|(i, _)| *i) != Some(idx) {
+            self.current = Some((idx, self.shared.block(idx)?));
+        }
+
+        let (_, block) = self.current.as_ref().unwrap();
+        let start = ((offset % block_size) as usize).min(block.len());
+        Ok(&block[start..])
+    }
+
+    /// Reads into `buf` from the given offset, without moving the position of the handle.
+    ///
+    /// At most one block is read, `0` means the end of the file(s).
+    pub fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || offset >= self.shared.size {
+            return Ok(0);
+        }
+
+        let slice = self.slice_at(offset)?;
+        let size = buf.len().min(slice.len());
+        buf[..size].copy_from_slice(&slice[..size]);
+
+        Ok(size)
+    }
+}
+
+impl<R> Clone for SharedExactReader<R> {
+    /// Creates a new handle at the same position, sharing the file(s) and the block cache.
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            position: self.position,
+            current: self.current.clone(),
+        }
+    }
+}
+
+impl<R: ReadAt> Read for SharedExactReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.read_at(self.position, buf)?;
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<R: ReadAt> BufRead for SharedExactReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.position >= self.shared.size {
+            return Ok(&[]);
+        }
+
+        self.slice_at(self.position)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt as u64;
+    }
+}
+
+impl<R> Seek for SharedExactReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = calculate_seek(self.shared.size, self.position, pos)?;
+        Ok(self.position)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn new_reader(data: Vec<u8>) -> SharedExactReader<MultiFile<Cursor<Vec<u8>>>> {
+        let (a, b) = data.split_at(data.len() / 3);
+        let files = [a, b].map(|part| File {
+            size: part.len() as u64,
+            file: Cursor::new(part.to_vec()),
+            filename: "cursor".to_string(),
+        });
+
+        SharedExactReader::new_multi(MultiFile::new(files.into())).with_cache_limits(100, 8)
+    }
+
+    #[test]
+    fn test_threads() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let reader = new_reader(data.clone());
+        assert_send_sync(&reader);
+
+        std::thread::scope(|scope| {
+            for start in [0, 1234, 5000, 9000] {
+                let mut reader = reader.clone();
+                let data = &data;
+                scope.spawn(move || {
+                    reader.seek(SeekFrom::Start(start)).unwrap();
+                    let mut out = Vec::new();
+                    reader.read_to_end(&mut out).unwrap();
+                    assert_eq!(out, data[start as usize..]);
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_handles() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut a = new_reader(data.clone());
+        let mut b = a.clone();
+
+        let mut buf = [0u8; 150];
+        a.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, data[..150]);
+        assert_eq!(b.stream_position().unwrap(), 0);
+
+        b.seek(SeekFrom::End(-6)).unwrap();
+        let mut line = Vec::new();
+        b.read_until(255, &mut line).unwrap();
+        assert_eq!(line, data[250..]);
+
+        assert_eq!(a.read_at(84, &mut buf).unwrap(), 16);
+        assert_eq!(buf[..16], data[84..100]);
+    }
+
+    #[test]
+    fn test_cache_limits() {
+        let data: Vec<u8> = (0..=255).collect();
+
+        for max_blocks in [0, 1, 8, 20] {
+            let mut reader = new_reader(data.clone()).with_cache_limits(10, max_blocks);
+            let mut out = Vec::new();
+            reader.read_to_end(&mut out).unwrap();
+            assert_eq!(out, data);
+
+            let cached: usize = reader
+                .shared
+                .shards
+                .iter()
+                .map(|shard| shard.lock().unwrap().blocks.len())
+                .sum();
+            assert_eq!(cached, max_blocks);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+/// The I/O counters of a reader, see [`ExactReader::stats`](crate::ExactReader::stats).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// The calls to `read` on the inner reader.
+    pub reads: usize,
+    /// The calls to `seek` on the inner reader.
+    pub seeks: usize,
+
+    /// The bytes requested from the inner reader.
+    pub bytes_requested: u64,
+    /// The bytes actually read from the inner reader.
+    pub bytes_read: u64,
+    /// The bytes served from the buffer.
+    pub bytes_served: u64,
+
+    /// The seeks landing within `file_offset_view`, served without any I/O.
+    pub seek_hits: usize,
+    /// The seeks served by swapping a cached range in.
+    pub cache_hits: usize,
+    /// The seeks that went to the inner reader.
+    pub seek_misses: usize,
+
+    /// The times the buffer was replaced by a fresh one.
+    pub buffer_clears: usize,
+}
+
+/// The I/O counters of a file within a `MultiFile`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FileStats {
+    /// The calls to `read` on the file.
+    pub reads: usize,
+    /// The calls to `seek` on the file.
+    pub seeks: usize,
+}
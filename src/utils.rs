@@ -1,20 +1,189 @@
+use std::ops::Range;
+
 /// Calculates the new seek position in the file based on the current offset
+///
+/// Seeking before the start, or past `u64::MAX`, fails with `InvalidInput`.
 pub fn calculate_seek(
-    size: usize,
-    current_offset: usize,
+    size: u64,
+    current_offset: u64,
     pos: std::io::SeekFrom,
 ) -> std::io::Result<u64> {
-    let (base_pos, offset) = match pos {
-        std::io::SeekFrom::Start(o) => (0, o as i64),
-        std::io::SeekFrom::End(o) => (size as i64, o),
-        std::io::SeekFrom::Current(o) => (current_offset as i64, o),
+    let new_pos = match pos {
+        std::io::SeekFrom::Start(o) => Some(o),
+        std::io::SeekFrom::End(o) => size.checked_add_signed(o),
+        std::io::SeekFrom::Current(o) => current_offset.checked_add_signed(o),
+    };
+
+    new_pos.ok_or_else(|| std::io::ErrorKind::InvalidInput.into())
+}
+
+/// Calculates the parts of `wanted` that are not covered by `cached`,
+/// as the gaps before and after it.
+pub fn range_difference(wanted: Range<u64>, cached: Range<u64>) -> (Range<u64>, Range<u64>) {
+    let front_end = wanted.end.min(cached.start).max(wanted.start);
+    let back_start = wanted.start.max(cached.end).min(wanted.end);
+
+    (wanted.start..front_end, back_start..wanted.end)
+}
+
+/// Finds the first `byte` at or after `from` in the concatenation of `head` and `tail`.
+pub fn find_byte(head: &[u8], tail: &[u8], byte: u8, from: usize) -> Option<usize> {
+    if let Some(pos) = head
+        .get(from..)
+        .and_then(|head| head.iter().position(|&b| b == byte))
+    {
+        return Some(from + pos);
+    }
+
+    let tail_from = from.saturating_sub(head.len());
+    let pos = tail.get(tail_from..)?.iter().position(|&b| b == byte)?;
+    Some(head.len() + tail_from + pos)
+}
+
+/// Finds the last `needle` in the concatenation of `head` and `tail`,
+/// including the matches straddling both.
+pub fn rfind_pattern(head: &[u8], tail: &[u8], needle: &[u8]) -> Option<usize> {
+    let len = head.len() + tail.len();
+    let byte_at = |idx: usize| {
+        if idx < head.len() {
+            head[idx]
+        } else {
+            tail[idx - head.len()]
+        }
     };
 
-    let new_pos = base_pos + offset;
+    (0..=len.checked_sub(needle.len())?)
+        .rev()
+        .find(|&start| (0..needle.len()).all(|k| byte_at(start + k) == needle[k]))
+}
+
+/// Finds the first `needle` at or after `from` in the concatenation of `head` and `tail`,
+/// including the matches straddling both.
+pub fn find_pattern(head: &[u8], tail: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    let len = head.len() + tail.len();
+    let Some((&first, _)) = needle.split_first() else {
+        return (from <= len).then_some(from);
+    };
+    let byte_at = |idx: usize| {
+        if idx < head.len() {
+            head[idx]
+        } else {
+            tail[idx - head.len()]
+        }
+    };
+
+    let mut start = from;
+    while start + needle.len() <= len {
+        // the candidates are found by their first byte
+        let candidate = find_byte(head, tail, first, start)?;
+        if candidate + needle.len() > len {
+            return None;
+        }
+        if (1..needle.len()).all(|k| byte_at(candidate + k) == needle[k]) {
+            return Some(candidate);
+        }
+        start = candidate + 1;
+    }
+
+    None
+}
+
+/// Compares the names in natural order, so that `part-9` comes before `part-10`.
+///
+/// The runs of ASCII digits are compared by value, with the fewer leading zeros first on a tie,
+/// and the rest is compared as is.
+pub fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    /// Splits the next run of digits or non-digits from the name.
+    fn next_run(name: &str) -> (&str, &str) {
+        let is_digit = name.starts_with(|c: char| c.is_ascii_digit());
+        let end = name
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(name.len());
+        name.split_at(end)
+    }
+
+    let (mut a, mut b) = (a, b);
+    while !a.is_empty() && !b.is_empty() {
+        let ((run_a, rest_a), (run_b, rest_b)) = (next_run(a), next_run(b));
+
+        let both_digits = run_a.starts_with(|c: char| c.is_ascii_digit())
+            && run_b.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = if both_digits {
+            let (value_a, value_b) = (run_a.trim_start_matches('0'), run_b.trim_start_matches('0'));
+            value_a
+                .len()
+                .cmp(&value_b.len())
+                .then_with(|| value_a.cmp(value_b))
+                .then_with(|| run_a.len().cmp(&run_b.len()))
+        } else {
+            run_a.cmp(run_b)
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+
+        (a, b) = (rest_a, rest_b);
+    }
+
+    a.len().cmp(&b.len())
+}
+
+/// Checks whether the name matches the pattern, where `*` matches any run of characters
+/// and `?` matches any single character.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // the last `*` and the position in the name it was tried at
+    let mut backtrack = None;
 
-    if new_pos.is_negative() {
-        return Err(std::io::ErrorKind::InvalidInput.into());
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => {
+                // the last `*` takes one more character
+                let Some((star, at)) = backtrack else {
+                    return false;
+                };
+                backtrack = Some((star, at + 1));
+                p = star + 1;
+                n = at + 1;
+            }
+        }
     }
 
-    Ok(new_pos as u64)
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = [
+            "part-10", "part-9", "part-09", "part-1a", "part-", "b", "a10b2",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            ["a10b2", "b", "part-", "part-1a", "part-9", "part-09", "part-10"]
+        );
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("part-*.bin", "part-10.bin"));
+        assert!(wildcard_match("*a*b?", "xaab_"));
+        assert!(!wildcard_match("part-*.bin", "part-1.bin.tmp"));
+        assert!(!wildcard_match("?", ""));
+    }
 }
@@ -330,12 +330,16 @@ impl<T> VecDeque<T> {
         if self.len > count {
             self.head = self.to_physical_idx(count);
             self.len -= count
+        } else {
+            self.clear()
         }
     }
 
     pub fn clear_back(&mut self, count: usize) {
         if self.len > count {
             self.len -= count
+        } else {
+            self.clear()
         }
     }
 
@@ -349,7 +353,7 @@ impl<T> VecDeque<T> {
         self.reserve(extend_from.len());
         let tail = self.to_physical_idx(self.len);
 
-        unsafe { std::ptr::copy_nonoverlapping(extend_from.as_ptr(), self.ptr().add(tail), len) }
+        unsafe { self.copy_slice(tail, extend_from) }
         self.len += len;
     }
 
@@ -363,13 +367,123 @@ impl<T> VecDeque<T> {
         self.reserve(len);
         self.head = self.wrap_sub(self.head, len);
 
-        unsafe {
-            std::ptr::copy_nonoverlapping(extend_from.as_ptr(), self.ptr().add(self.head), len)
-        }
+        unsafe { self.copy_slice(self.head, extend_from) }
 
         self.len += len;
     }
 }
+
+impl VecDeque<u8> {
+    /// Reserves `len` bytes after the back and returns them zeroed, as up to two slices.
+    ///
+    /// The bytes are not part of the deque until they are committed with `commit_back`.
+    pub fn spare_back(&mut self, len: usize) -> (&mut [u8], &mut [u8]) {
+        if len == 0 {
+            return (&mut [], &mut []);
+        }
+
+        self.reserve(len);
+        let start = self.to_physical_idx(self.len);
+        // SAFETY: the `len` bytes after the back are reserved and not initialized
+        unsafe { self.spare_range(start, len, true) }
+    }
+
+    /// Returns the space of the last `spare_back` again, without zeroing it.
+    ///
+    /// # Safety
+    ///
+    /// `spare_back(len)` must have been called before, and the deque must not be modified since.
+    pub unsafe fn spare_back_unzeroed(&mut self, len: usize) -> (&mut [u8], &mut [u8]) {
+        if len == 0 {
+            return (&mut [], &mut []);
+        }
+
+        let start = self.to_physical_idx(self.len);
+        unsafe { self.spare_range(start, len, false) }
+    }
+
+    /// Reserves `len` bytes before the front and returns them zeroed, as up to two slices.
+    ///
+    /// The bytes are not part of the deque until they are committed with `commit_front`.
+    pub fn spare_front(&mut self, len: usize) -> (&mut [u8], &mut [u8]) {
+        if len == 0 {
+            return (&mut [], &mut []);
+        }
+
+        self.reserve(len);
+        let start = self.wrap_sub(self.head, len);
+        // SAFETY: the `len` bytes before the front are reserved and not initialized
+        unsafe { self.spare_range(start, len, true) }
+    }
+
+    /// Returns the space of the last `spare_front` again, without zeroing it.
+    ///
+    /// # Safety
+    ///
+    /// `spare_front(len)` must have been called before, and the deque must not be modified since.
+    pub unsafe fn spare_front_unzeroed(&mut self, len: usize) -> (&mut [u8], &mut [u8]) {
+        if len == 0 {
+            return (&mut [], &mut []);
+        }
+
+        let start = self.wrap_sub(self.head, len);
+        unsafe { self.spare_range(start, len, false) }
+    }
+
+    /// Appends the first `count` bytes returned by the last `spare_back`.
+    ///
+    /// # Safety
+    ///
+    /// `count` must not exceed the length passed to the last `spare_back`,
+    /// and the deque must not be modified in between.
+    pub unsafe fn commit_back(&mut self, count: usize) {
+        debug_assert!(self.len + count <= self.capacity());
+        self.len += count;
+    }
+
+    /// Prepends the last `count` bytes returned by the last `spare_front`.
+    ///
+    /// # Safety
+    ///
+    /// `count` must not exceed the length passed to the last `spare_front`,
+    /// and the deque must not be modified in between.
+    pub unsafe fn commit_front(&mut self, count: usize) {
+        debug_assert!(self.len + count <= self.capacity());
+        self.head = self.wrap_sub(self.head, count);
+        self.len += count;
+    }
+
+    /// Returns the physical range of `len` bytes from `start`, wrapping around, optionally zeroed.
+    ///
+    /// # Safety
+    ///
+    /// The range must be within the capacity and outside of the initialized elements,
+    /// and it must be zeroed already when `zero` is `false`.
+    unsafe fn spare_range(
+        &mut self,
+        start: usize,
+        len: usize,
+        zero: bool,
+    ) -> (&mut [u8], &mut [u8]) {
+        let head_room = self.capacity() - start;
+        let (first, second) = if len <= head_room {
+            (start..start + len, 0..0)
+        } else {
+            (start..self.capacity(), 0..len - head_room)
+        };
+
+        unsafe {
+            if zero {
+                std::ptr::write_bytes(self.ptr().add(first.start), 0, first.len());
+                std::ptr::write_bytes(self.ptr().add(second.start), 0, second.len());
+            }
+            (
+                &mut *self.buffer_range(first),
+                &mut *self.buffer_range(second),
+            )
+        }
+    }
+}
 impl<T> VecDeque<T> {
     #[inline]
     #[must_use]
@@ -99,6 +99,8 @@ impl<T> RawVec<T> {
     pub fn capacity(&self) -> usize {
         self.cap
     }
+    // `is_multiple_of` would raise the minimum supported Rust version to 1.87
+    #[allow(clippy::manual_is_multiple_of)]
     fn current_memory(&self) -> Option<(Unique<u8>, Layout)> {
         if self.cap == 0 {
             None
@@ -110,7 +112,7 @@ impl<T> RawVec<T> {
                 let align = std::mem::align_of::<T>();
                 let size = std::mem::size_of::<T>() * self.cap;
                 let layout = Layout::from_size_align_unchecked(size, align);
-                Some((self.ptr.cast().into(), layout))
+                Some((self.ptr.cast(), layout))
             }
         }
     }
@@ -199,6 +201,7 @@ impl<T> RawVec<T> {
         Ok(())
     }
     #[cfg(not(no_global_oom_handling))]
+    #[allow(clippy::manual_is_multiple_of)]
     fn shrink(&mut self, cap: usize) -> Result<(), TryReserveError> {
         assert!(
             cap <= self.capacity(),
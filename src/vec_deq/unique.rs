@@ -27,6 +27,8 @@ impl<T: Sized> Unique<T> {
     /// sentinel value. Types that lazily allocate must track initialization by
     /// some other means.
     #[inline]
+    // `std::ptr::dangling_mut` would raise the minimum supported Rust version to 1.84
+    #[allow(clippy::manual_dangling_ptr)]
     pub const fn dangling() -> Self {
         // SAFETY: mem::align_of() returns a valid, non-null pointer. The
         // conditions to call new_unchecked() are thus respected.